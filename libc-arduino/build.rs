@@ -2,7 +2,14 @@ extern crate carguino_build;
 
 use carguino_build::Config;
 
+use std::env;
+
 pub fn main() {
+    // The simulated board uses the host's C library, see `src/host.rs`.
+    if env::var_os("CARGO_FEATURE_HOST_SIM").is_some() {
+        return;
+    }
+
     let config = Config::new().unwrap();
     config.bindgen()
           .options(|options| {
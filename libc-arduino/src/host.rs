@@ -0,0 +1,39 @@
+// Hand-written subset of `libc.h` used when building for the simulated board. These symbols are
+// resolved against the host's C library.
+
+pub type size_t = usize;
+
+extern "C" {
+    pub fn malloc(size: size_t) -> *mut c_void;
+    pub fn free(ptr: *mut c_void);
+    pub fn realloc(ptr: *mut c_void, size: size_t) -> *mut c_void;
+
+    pub fn tan(x: f64) -> f64;
+    pub fn tanf(x: f32) -> f32;
+    pub fn asin(x: f64) -> f64;
+    pub fn asinf(x: f32) -> f32;
+    pub fn acos(x: f64) -> f64;
+    pub fn acosf(x: f32) -> f32;
+    pub fn atan(x: f64) -> f64;
+    pub fn atanf(x: f32) -> f32;
+    pub fn atan2(y: f64, x: f64) -> f64;
+    pub fn atan2f(y: f32, x: f32) -> f32;
+    pub fn sinh(x: f64) -> f64;
+    pub fn sinhf(x: f32) -> f32;
+    pub fn cosh(x: f64) -> f64;
+    pub fn coshf(x: f32) -> f32;
+    pub fn tanh(x: f64) -> f64;
+    pub fn tanhf(x: f32) -> f32;
+    pub fn cbrt(x: f64) -> f64;
+    pub fn cbrtf(x: f32) -> f32;
+    pub fn hypot(x: f64, y: f64) -> f64;
+    pub fn hypotf(x: f32, y: f32) -> f32;
+    pub fn expm1(x: f64) -> f64;
+    pub fn expm1f(x: f32) -> f32;
+    pub fn log1p(x: f64) -> f64;
+    pub fn log1pf(x: f32) -> f32;
+    pub fn fmax(x: f64, y: f64) -> f64;
+    pub fn fmaxf(x: f32, y: f32) -> f32;
+    pub fn fmin(x: f64, y: f64) -> f64;
+    pub fn fminf(x: f32, y: f32) -> f32;
+}
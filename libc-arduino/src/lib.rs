@@ -4,7 +4,7 @@
 
 use core::fmt;
 
-#[cfg(target_arch = "arm")]
+#[cfg(all(target_arch = "arm", not(feature = "host-sim")))]
 mod platform {
     pub type c_char = u8;
     pub type c_int = i32;
@@ -12,7 +12,7 @@ mod platform {
     pub type c_double = f64;
 }
 
-#[cfg(target_arch = "avr")]
+#[cfg(all(target_arch = "avr", not(feature = "host-sim")))]
 mod platform {
     pub type c_char = i8;
     pub type c_int = i16;
@@ -20,6 +20,14 @@ mod platform {
     pub type c_double = f32;
 }
 
+#[cfg(feature = "host-sim")]
+mod platform {
+    pub type c_char = i8;
+    pub type c_int = i32;
+    pub type c_uint = u32;
+    pub type c_double = f64;
+}
+
 pub type c_char = platform::c_char;
 pub type c_schar = i8;
 pub type c_uchar = u8;
@@ -51,4 +59,8 @@ impl fmt::Debug for c_void {
     }
 }
 
+#[cfg(not(feature = "host-sim"))]
 include!(concat!(env!("OUT_DIR"), "/libc.rs"));
+
+#[cfg(feature = "host-sim")]
+include!("host.rs");
@@ -0,0 +1,22 @@
+extern crate carguino_build;
+
+use carguino_build::Config;
+
+pub fn main() {
+    let config = Config::new().unwrap();
+
+    config.builder()
+          .library_sources("Wire")
+          .source("src/ffi/rduino_i2c.cpp")
+          .build("rduino_i2c")
+          .unwrap();
+
+    config.bindgen()
+          .options(|options| {
+              options.ctypes_prefix("::rduino::platform::raw")
+                     .whitelisted_type("RduinoI2c.+")
+                     .whitelisted_function("rduino_i2c_.+")
+          })
+          .generate("src/ffi/rduino_i2c.hpp")
+          .unwrap();
+}
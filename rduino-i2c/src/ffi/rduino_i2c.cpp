@@ -0,0 +1,89 @@
+#include "rduino_i2c.hpp"
+
+#include <Wire.h>
+
+extern "C" {
+
+void rduino_i2c_begin(void) {
+    Wire.begin();
+}
+
+void rduino_i2c_end(void) {
+    Wire.end();
+}
+
+void rduino_i2c_set_clock(uint32_t clock) {
+    Wire.setClock(clock);
+}
+
+RduinoI2cStatus rduino_i2c_write(uint8_t address, const uint8_t *buffer, size_t length, bool stop) {
+    Wire.beginTransmission(address);
+    if (Wire.write(buffer, length) != length) {
+        // Nothing has been sent yet; the next transmission discards the buffered data.
+        return RduinoI2cStatus::DataTooLong;
+    }
+
+    switch (Wire.endTransmission(stop)) {
+        case 0:
+            return RduinoI2cStatus::Success;
+        case 1:
+            return RduinoI2cStatus::DataTooLong;
+        case 2:
+            return RduinoI2cStatus::AddressNack;
+        case 3:
+            return RduinoI2cStatus::DataNack;
+        case 5:
+            return RduinoI2cStatus::Timeout;
+        default:
+            return RduinoI2cStatus::Other;
+    }
+}
+
+size_t rduino_i2c_read(uint8_t address, uint8_t *buffer, size_t length, bool stop) {
+    if (length > UINT8_MAX) {
+        length = UINT8_MAX;
+    }
+
+    size_t received = Wire.requestFrom(address, (uint8_t)length, (uint8_t)stop);
+    for (size_t i = 0; i < received; i++) {
+        buffer[i] = Wire.read();
+    }
+    return received;
+}
+
+
+// Slave mode
+static RduinoI2cReceiveHandler receive_handler = nullptr;
+
+static void receive_trampoline(int count) {
+    if (receive_handler) {
+        receive_handler(count);
+    }
+}
+
+void rduino_i2c_begin_slave(uint8_t address) {
+    Wire.begin(address);
+}
+
+void rduino_i2c_on_receive(RduinoI2cReceiveHandler handler) {
+    receive_handler = handler;
+    Wire.onReceive(receive_trampoline);
+}
+
+void rduino_i2c_on_request(RduinoI2cRequestHandler handler) {
+    Wire.onRequest(handler);
+}
+
+size_t rduino_i2c_available(void) {
+    return Wire.available();
+}
+
+int rduino_i2c_read_byte(void) {
+    return Wire.read();
+}
+
+size_t rduino_i2c_write_bytes(const uint8_t *buffer, size_t length) {
+    return Wire.write(buffer, length);
+}
+
+}
@@ -0,0 +1,37 @@
+#pragma once
+
+#include <Arduino.h>
+#include <cstdlib>
+#include <cstdint>
+
+extern "C" {
+
+enum class RduinoI2cStatus {
+    Success,
+    DataTooLong,
+    AddressNack,
+    DataNack,
+    Other,
+    Timeout
+};
+
+void rduino_i2c_begin(void);
+void rduino_i2c_end(void);
+void rduino_i2c_set_clock(uint32_t clock);
+
+RduinoI2cStatus rduino_i2c_write(uint8_t address, const uint8_t *buffer, size_t length, bool stop);
+size_t rduino_i2c_read(uint8_t address, uint8_t *buffer, size_t length, bool stop);
+
+// Slave mode
+typedef void (* RduinoI2cReceiveHandler)(size_t count);
+typedef void (* RduinoI2cRequestHandler)(void);
+
+void rduino_i2c_begin_slave(uint8_t address);
+void rduino_i2c_on_receive(RduinoI2cReceiveHandler handler);
+void rduino_i2c_on_request(RduinoI2cRequestHandler handler);
+
+size_t rduino_i2c_available(void);
+int rduino_i2c_read_byte(void);
+size_t rduino_i2c_write_bytes(const uint8_t *buffer, size_t length);
+
+}
@@ -1,3 +1,199 @@
-#[test]
-fn it_works() {
+//! I2C (TWI) driver built on the Arduino `Wire` library.
+//!
+//! The bus is obtained once with `I2c::take`, after which the board acts as the bus master and can
+//! exchange data with devices using their 7-bit addresses. Failed transfers are reported as
+//! `io::Error`s, e.g. `AddressNack` if no device answered to the address. The board can also act
+//! as a slave device with its own address, see the `slave` module.
+//!
+//! See also: the [`Wire` library] from the Arduino reference.
+//!
+//! [`Wire` library]: https://www.arduino.cc/en/Reference/Wire
+#![no_std]
+
+#![feature(optin_builtin_traits)]
+
+extern crate rduino;
+
+#[macro_use]
+mod macros;
+
+pub mod ffi;
+pub mod slave;
+
+use ffi::RduinoI2cStatus;
+use slave::I2cSlave;
+
+use rduino::interrupts;
+use rduino::io;
+use rduino::sync::Mutex;
+
+static TAKEN: Mutex<bool> = Mutex::new(false);
+
+/// The clock speed of the bus.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum BusSpeed {
+    /// Standard mode (100 kHz).
+    Standard,
+
+    /// Fast mode (400 kHz).
+    Fast,
+
+    /// Fast mode plus (1 MHz).
+    ///
+    /// **Note**: Not supported by all boards.
+    FastPlus,
+
+    /// A custom clock speed in hertz.
+    Custom(u32)
 }
+
+impl BusSpeed {
+    #[inline]
+    fn to_hertz(self) -> u32 {
+        match self {
+            BusSpeed::Standard => 100_000,
+            BusSpeed::Fast => 400_000,
+            BusSpeed::FastPlus => 1_000_000,
+            BusSpeed::Custom(hertz) => hertz
+        }
+    }
+}
+
+impl Default for BusSpeed {
+    #[inline]
+    fn default() -> BusSpeed {
+        BusSpeed::Standard
+    }
+}
+
+fn status_to_result(status: RduinoI2cStatus) -> io::Result<()> {
+    match status {
+        RduinoI2cStatus::Success => Ok(()),
+        RduinoI2cStatus::DataTooLong => Err(io::Error::BufferFull),
+        RduinoI2cStatus::AddressNack => Err(io::Error::AddressNack),
+        RduinoI2cStatus::DataNack => Err(io::Error::DataNack),
+        RduinoI2cStatus::Timeout => Err(io::Error::TimedOut),
+        RduinoI2cStatus::Other => Err(io::Error::Other)
+    }
+}
+
+
+
+/// The I2C bus.
+///
+/// The bus is initialized in master mode when it is taken and shut down when it is dropped.
+///
+/// **Note**: The `Wire` library buffers transfers. On most AVR boards the buffer holds 32 bytes,
+/// longer transfers fail with `BufferFull` or `UnexpectedEof`.
+#[derive(Debug)]
+pub struct I2c {
+    _private: ()
+}
+
+impl I2c {
+    /// Take and initialize the I2C bus.
+    ///
+    /// Returns `None` if the bus was already taken and not dropped since.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine. Because the
+    /// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid
+    /// an unsafe API.
+    pub fn take() -> Option<I2c> {
+        if interrupts::inside_isr() {
+            panic!("`I2c::take` called from within an interrupt service routine");
+        }
+
+        {
+            let mut taken = TAKEN.lock();
+            if *taken {
+                return None;
+            }
+            *taken = true;
+        }
+
+        unsafe {
+            ffi::rduino_i2c_begin();
+        }
+        Some(I2c { _private: () })
+    }
+
+    /// Set the clock speed of the bus.
+    #[inline]
+    pub fn set_speed(&mut self, speed: BusSpeed) {
+        unsafe {
+            ffi::rduino_i2c_set_clock(speed.to_hertz());
+        }
+    }
+
+    /// Write some bytes to the device with the given address.
+    ///
+    /// # Errors
+    /// If the device did not acknowledge its address or the data, the bytes do not fit in the
+    /// transmit buffer or the bus timed out.
+    #[inline]
+    pub fn write(&mut self, address: u8, bytes: &[u8]) -> io::Result<()> {
+        self.write_inner(address, bytes, true)
+    }
+
+    /// Read enough bytes from the device with the given address to fill the buffer.
+    ///
+    /// # Errors
+    /// If the device did not acknowledge its address or sent fewer bytes than requested.
+    #[inline]
+    pub fn read(&mut self, address: u8, buf: &mut [u8]) -> io::Result<()> {
+        self.read_inner(address, buf, true)
+    }
+
+    /// Write some bytes to the device and read its response using a repeated start.
+    ///
+    /// The bus is not released between writing and reading, which is typically used to select a
+    /// register and read its value.
+    ///
+    /// # Errors
+    /// The same as for `write` and `read`.
+    pub fn write_read(&mut self, address: u8, bytes: &[u8], buf: &mut [u8]) -> io::Result<()> {
+        self.write_inner(address, bytes, false)?;
+        self.read_inner(address, buf, true)
+    }
+
+    /// Switch to slave mode, answering to the given 7-bit address.
+    ///
+    /// Handlers for data written and requested by the master can be set on the returned slave.
+    #[inline]
+    pub fn into_slave(self, address: u8) -> I2cSlave {
+        I2cSlave::__new(self, address)
+    }
+
+    fn write_inner(&mut self, address: u8, bytes: &[u8], stop: bool) -> io::Result<()> {
+        let status = unsafe { ffi::rduino_i2c_write(address, bytes.as_ptr(), bytes.len(), stop) };
+        status_to_result(status)
+    }
+
+    fn read_inner(&mut self, address: u8, buf: &mut [u8], stop: bool) -> io::Result<()> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        let received = unsafe { ffi::rduino_i2c_read(address, buf.as_mut_ptr(), buf.len(), stop) };
+        if received == 0 {
+            Err(io::Error::AddressNack)
+        } else if received < buf.len() {
+            Err(io::Error::UnexpectedEof)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for I2c {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::rduino_i2c_end();
+        }
+        *TAKEN.lock() = false;
+    }
+}
+
+impl !Send for I2c {}
+impl !Sync for I2c {}
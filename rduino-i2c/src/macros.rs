@@ -0,0 +1,47 @@
+/// Build a `ReceiveHandler` from a closure.
+///
+/// The closure is called with a `Received` through which the bytes sent by the master can be read.
+/// It runs inside of an interrupt service routine, so the same restrictions as for `rduino_isr!`
+/// apply: it must not capture anything and shared state can only be accessed through a
+/// `sync::Mutex`.
+#[macro_export]
+macro_rules! rduino_i2c_on_receive {
+    ($f:expr) => {{
+        extern "C" fn __handler(_count: usize) {
+            #[inline(always)]
+            fn __call_closure<F: Fn(&mut $crate::slave::Received) + Send + Sync>(f: F) {
+                f(&mut $crate::slave::Received::__new());
+            }
+
+            $crate::slave::__enter_isr();
+            __call_closure($f);
+            $crate::slave::__exit_isr();
+        }
+
+        $crate::slave::ReceiveHandler::__new(__handler)
+    }}
+}
+
+/// Build a `RequestHandler` from a closure.
+///
+/// The closure is called with a `Response` to which the bytes to send to the master can be
+/// written. It runs inside of an interrupt service routine, so the same restrictions as for
+/// `rduino_isr!` apply: it must not capture anything and shared state can only be accessed
+/// through a `sync::Mutex`.
+#[macro_export]
+macro_rules! rduino_i2c_on_request {
+    ($f:expr) => {{
+        extern "C" fn __handler() {
+            #[inline(always)]
+            fn __call_closure<F: Fn(&mut $crate::slave::Response) + Send + Sync>(f: F) {
+                f(&mut $crate::slave::Response::__new());
+            }
+
+            $crate::slave::__enter_isr();
+            __call_closure($f);
+            $crate::slave::__exit_isr();
+        }
+
+        $crate::slave::RequestHandler::__new(__handler)
+    }}
+}
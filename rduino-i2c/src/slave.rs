@@ -0,0 +1,191 @@
+//! Slave (peripheral) mode.
+//!
+//! In slave mode the board answers to its own address on the bus. Data is exchanged through
+//! handlers that run when the master writes to or reads from the board. Handlers are built with the
+//! `rduino_i2c_on_receive!` and `rduino_i2c_on_request!` macros and run inside of an interrupt
+//! service routine, i.e. `interrupts::inside_isr()` returns `true` while they run.
+
+#[doc(hidden)]
+pub use rduino::interrupts::{__enter_isr, __exit_isr};
+
+use ffi;
+use I2c;
+
+use rduino::io;
+
+/// A handler for data written by the master.
+///
+/// A handler can be constructed with the `rduino_i2c_on_receive!` macro.
+#[derive(Debug)]
+pub struct ReceiveHandler {
+    #[doc(hidden)]
+    pub routine: extern fn(usize)
+}
+
+impl ReceiveHandler {
+    #[doc(hidden)]
+    #[inline]
+    pub const fn __new(routine: extern fn(usize)) -> ReceiveHandler {
+        ReceiveHandler {
+            routine: routine
+        }
+    }
+}
+
+/// A handler for data requested by the master.
+///
+/// A handler can be constructed with the `rduino_i2c_on_request!` macro.
+#[derive(Debug)]
+pub struct RequestHandler {
+    #[doc(hidden)]
+    pub routine: extern fn()
+}
+
+impl RequestHandler {
+    #[doc(hidden)]
+    #[inline]
+    pub const fn __new(routine: extern fn()) -> RequestHandler {
+        RequestHandler {
+            routine: routine
+        }
+    }
+}
+
+
+
+/// The data written by the master, passed to a `ReceiveHandler`.
+#[derive(Debug)]
+pub struct Received {
+    _private: ()
+}
+
+impl Received {
+    #[doc(hidden)]
+    #[inline]
+    pub fn __new() -> Received {
+        Received { _private: () }
+    }
+
+    /// The number of bytes left to read.
+    #[inline]
+    pub fn available(&self) -> usize {
+        unsafe { ffi::rduino_i2c_available() }
+    }
+}
+
+impl io::Read for Received {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut count = 0;
+        while count < buf.len() {
+            let value = unsafe { ffi::rduino_i2c_read_byte() };
+            if value < 0 {
+                break;
+            }
+            buf[count] = value as u8;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl !Send for Received {}
+
+
+
+/// The response to the master, passed to a `RequestHandler`.
+#[derive(Debug)]
+pub struct Response {
+    _private: ()
+}
+
+impl Response {
+    #[doc(hidden)]
+    #[inline]
+    pub fn __new() -> Response {
+        Response { _private: () }
+    }
+}
+
+impl io::Write for Response {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            Ok(0)
+        } else {
+            Ok(unsafe { ffi::rduino_i2c_write_bytes(buf.as_ptr(), buf.len()) })
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl !Send for Response {}
+
+
+
+/// The I2C bus in slave mode.
+///
+/// Created by `I2c::into_slave`. When this structure is dropped, the handlers are removed and the
+/// bus is shut down.
+#[derive(Debug)]
+pub struct I2cSlave {
+    bus: Option<I2c>
+}
+
+impl I2cSlave {
+    #[doc(hidden)]
+    #[inline]
+    pub fn __new(bus: I2c, address: u8) -> I2cSlave {
+        unsafe {
+            ffi::rduino_i2c_begin_slave(address);
+        }
+        I2cSlave { bus: Some(bus) }
+    }
+
+    /// Set the handler that is called when the master writes to this board.
+    ///
+    /// Replaces any previously set handler.
+    #[inline]
+    pub fn on_receive(&mut self, handler: ReceiveHandler) {
+        unsafe {
+            ffi::rduino_i2c_on_receive(Some(handler.routine));
+        }
+    }
+
+    /// Set the handler that is called when the master reads from this board.
+    ///
+    /// Replaces any previously set handler. If the handler writes nothing, the master reads
+    /// `0xFF` bytes.
+    #[inline]
+    pub fn on_request(&mut self, handler: RequestHandler) {
+        unsafe {
+            ffi::rduino_i2c_on_request(Some(handler.routine));
+        }
+    }
+
+    /// Remove the handlers and switch back to master mode.
+    pub fn into_master(mut self) -> I2c {
+        self.clear_handlers();
+        unsafe {
+            ffi::rduino_i2c_end();
+            ffi::rduino_i2c_begin();
+        }
+        self.bus.take().unwrap()
+    }
+
+    fn clear_handlers(&mut self) {
+        unsafe {
+            ffi::rduino_i2c_on_receive(None);
+            ffi::rduino_i2c_on_request(None);
+        }
+    }
+}
+
+impl Drop for I2cSlave {
+    fn drop(&mut self) {
+        self.clear_handlers();
+    }
+}
@@ -0,0 +1,22 @@
+extern crate carguino_build;
+
+use carguino_build::Config;
+
+pub fn main() {
+    let config = Config::new().unwrap();
+
+    config.builder()
+          .library_sources("SPI")
+          .source("src/ffi/rduino_spi.cpp")
+          .build("rduino_spi")
+          .unwrap();
+
+    config.bindgen()
+          .options(|options| {
+              options.ctypes_prefix("::rduino::platform::raw")
+                     .whitelisted_type("RduinoSpi.+")
+                     .whitelisted_function("rduino_spi_.+")
+          })
+          .generate("src/ffi/rduino_spi.hpp")
+          .unwrap();
+}
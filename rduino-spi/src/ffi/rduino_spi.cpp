@@ -0,0 +1,48 @@
+#include "rduino_spi.hpp"
+
+#include <SPI.h>
+
+extern "C" {
+
+void rduino_spi_begin(void) {
+    SPI.begin();
+}
+
+void rduino_spi_end(void) {
+    SPI.end();
+}
+
+void rduino_spi_begin_transaction(uint32_t clock, RduinoSpiBitOrder bit_order, RduinoSpiMode mode) {
+    uint8_t real_mode;
+    switch (mode) {
+        case RduinoSpiMode::Mode0:
+            real_mode = SPI_MODE0;
+            break;
+        case RduinoSpiMode::Mode1:
+            real_mode = SPI_MODE1;
+            break;
+        case RduinoSpiMode::Mode2:
+            real_mode = SPI_MODE2;
+            break;
+        case RduinoSpiMode::Mode3:
+            real_mode = SPI_MODE3;
+            break;
+        default:
+            return;
+    }
+    SPI.beginTransaction(SPISettings(clock, bit_order == RduinoSpiBitOrder::LsbFirst ? LSBFIRST : MSBFIRST, real_mode));
+}
+
+void rduino_spi_end_transaction(void) {
+    SPI.endTransaction();
+}
+
+uint8_t rduino_spi_transfer(uint8_t value) {
+    return SPI.transfer(value);
+}
+
+void rduino_spi_transfer_bytes(uint8_t *buffer, size_t length) {
+    SPI.transfer(buffer, length);
+}
+
+}
@@ -0,0 +1,30 @@
+#pragma once
+
+#include <Arduino.h>
+#include <cstdlib>
+#include <cstdint>
+
+extern "C" {
+
+enum class RduinoSpiBitOrder {
+    MsbFirst,
+    LsbFirst
+};
+
+enum class RduinoSpiMode {
+    Mode0,
+    Mode1,
+    Mode2,
+    Mode3
+};
+
+void rduino_spi_begin(void);
+void rduino_spi_end(void);
+
+void rduino_spi_begin_transaction(uint32_t clock, RduinoSpiBitOrder bit_order, RduinoSpiMode mode);
+void rduino_spi_end_transaction(void);
+
+uint8_t rduino_spi_transfer(uint8_t value);
+void rduino_spi_transfer_bytes(uint8_t *buffer, size_t length);
+
+}
@@ -1,3 +1,245 @@
-#[test]
-fn it_works() {
+//! SPI master driver built on the Arduino `SPI` library.
+//!
+//! The bus is obtained once with `Spi::take`. Every exchange with a device happens inside a
+//! transaction started with `Spi::begin_transaction`, which applies the device's settings, selects
+//! the device through its chip-select pin and deselects it again when the transaction ends.
+//!
+//! See also: the [`SPI` library] from the Arduino reference.
+//!
+//! [`SPI` library]: https://www.arduino.cc/en/Reference/SPI
+#![no_std]
+
+#![feature(optin_builtin_traits)]
+
+extern crate rduino;
+
+pub mod ffi;
+
+use ffi::{RduinoSpiBitOrder, RduinoSpiMode};
+
+use rduino::interrupts;
+use rduino::pins::{BitOrder, DigitalPin, Output, PinLevel};
+use rduino::sync::Mutex;
+
+use core::mem;
+
+static TAKEN: Mutex<bool> = Mutex::new(false);
+
+/// SPI clock polarity and phase.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum SpiMode {
+    /// Clock idles low, data is sampled on the rising edge.
+    Mode0,
+
+    /// Clock idles low, data is sampled on the falling edge.
+    Mode1,
+
+    /// Clock idles high, data is sampled on the falling edge.
+    Mode2,
+
+    /// Clock idles high, data is sampled on the rising edge.
+    Mode3
+}
+
+impl SpiMode {
+    #[inline]
+    fn to_ffi(self) -> RduinoSpiMode {
+        match self {
+            SpiMode::Mode0 => RduinoSpiMode::Mode0,
+            SpiMode::Mode1 => RduinoSpiMode::Mode1,
+            SpiMode::Mode2 => RduinoSpiMode::Mode2,
+            SpiMode::Mode3 => RduinoSpiMode::Mode3
+        }
+    }
+}
+
+impl Default for SpiMode {
+    #[inline]
+    fn default() -> SpiMode {
+        SpiMode::Mode0
+    }
+}
+
+/// The settings used to communicate with a device.
+///
+/// The settings can be found in the device's datasheet. By default the clock runs at 4 MHz, the
+/// most significant bit is sent first and the mode is `Mode0`.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct SpiSettings {
+    clock: u32,
+    bit_order: BitOrder,
+    mode: SpiMode
+}
+
+impl SpiSettings {
+    /// Create settings with the default values.
+    pub fn new() -> SpiSettings {
+        SpiSettings {
+            clock: 4_000_000,
+            bit_order: BitOrder::MsbFirst,
+            mode: SpiMode::Mode0
+        }
+    }
+
+    /// Set the maximum clock speed in hertz.
+    ///
+    /// The actual speed is the highest speed the board supports that does not exceed this value.
+    #[inline]
+    pub fn clock(mut self, clock: u32) -> SpiSettings {
+        self.clock = clock;
+        self
+    }
+
+    /// Set the order in which the bits of a byte are sent.
+    #[inline]
+    pub fn bit_order(mut self, bit_order: BitOrder) -> SpiSettings {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Set the clock polarity and phase.
+    #[inline]
+    pub fn mode(mut self, mode: SpiMode) -> SpiSettings {
+        self.mode = mode;
+        self
+    }
+
+    fn bit_order_to_ffi(&self) -> RduinoSpiBitOrder {
+        match self.bit_order {
+            BitOrder::MsbFirst => RduinoSpiBitOrder::MsbFirst,
+            BitOrder::LsbFirst => RduinoSpiBitOrder::LsbFirst
+        }
+    }
+}
+
+impl Default for SpiSettings {
+    #[inline]
+    fn default() -> SpiSettings {
+        SpiSettings::new()
+    }
+}
+
+
+
+/// The SPI bus.
+///
+/// The bus is initialized when it is taken and shut down when it is dropped.
+#[derive(Debug)]
+pub struct Spi {
+    _private: ()
+}
+
+impl Spi {
+    /// Take and initialize the SPI bus.
+    ///
+    /// Returns `None` if the bus was already taken and not dropped since.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine. Because the
+    /// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid
+    /// an unsafe API.
+    pub fn take() -> Option<Spi> {
+        if interrupts::inside_isr() {
+            panic!("`Spi::take` called from within an interrupt service routine");
+        }
+
+        {
+            let mut taken = TAKEN.lock();
+            if *taken {
+                return None;
+            }
+            *taken = true;
+        }
+
+        unsafe {
+            ffi::rduino_spi_begin();
+        }
+        Some(Spi { _private: () })
+    }
+
+    /// Start a transaction with the device selected by the given chip-select pin.
+    ///
+    /// Applies the settings and selects the device by pulling the chip-select pin low. Returns a
+    /// guard through which data can be transferred. When the guard is dropped, the device is
+    /// deselected and the transaction ends.
+    pub fn begin_transaction<'a>(&'a mut self, settings: SpiSettings,
+                                 chip_select: &'a DigitalPin<Output>) -> Transaction<'a> {
+        unsafe {
+            ffi::rduino_spi_begin_transaction(settings.clock, settings.bit_order_to_ffi(),
+                                              settings.mode.to_ffi());
+        }
+        chip_select.write(PinLevel::Low);
+
+        Transaction {
+            _spi: self,
+            chip_select: chip_select
+        }
+    }
+}
+
+impl Drop for Spi {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::rduino_spi_end();
+        }
+        *TAKEN.lock() = false;
+    }
+}
+
+impl !Send for Spi {}
+impl !Sync for Spi {}
+
+
+
+/// A RAII implementation of an SPI transaction.
+///
+/// When this structure is dropped or `end` is called, the device is deselected and the transaction
+/// ends.
+#[must_use]
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    _spi: &'a mut Spi,
+    chip_select: &'a DigitalPin<Output>
+}
+
+impl<'a> Transaction<'a> {
+    /// Send a byte and return the byte received at the same time.
+    #[inline]
+    pub fn transfer(&mut self, byte: u8) -> u8 {
+        unsafe { ffi::rduino_spi_transfer(byte) }
+    }
+
+    /// Send the bytes in the given buffer, replacing them with the bytes received.
+    #[inline]
+    pub fn transfer_in_place(&mut self, buf: &mut [u8]) {
+        if !buf.is_empty() {
+            unsafe {
+                ffi::rduino_spi_transfer_bytes(buf.as_mut_ptr(), buf.len());
+            }
+        }
+    }
+
+    /// Send the bytes in the given buffer, discarding the bytes received.
+    #[inline]
+    pub fn write(&mut self, buf: &[u8]) {
+        for &byte in buf {
+            self.transfer(byte);
+        }
+    }
+
+    /// Deselect the device and end the transaction.
+    ///
+    /// This has the same effect as dropping the guard.
+    pub fn end(self) {
+        mem::drop(self)
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        self.chip_select.write(PinLevel::High);
+        unsafe {
+            ffi::rduino_spi_end_transaction();
+        }
+    }
 }
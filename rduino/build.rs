@@ -2,7 +2,23 @@ extern crate carguino_build;
 
 use carguino_build::Config;
 
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Features that enable optional parts of the C++ shim, with the define each one sets.
+const SHIM_FEATURES: &'static [(&'static str, &'static str)] = &[
+    ("CARGO_FEATURE_PIN_CHANGE_INTERRUPTS", "RDUINO_PIN_CHANGE_INTERRUPTS"),
+    ("CARGO_FEATURE_SAMD_TIMERS", "RDUINO_SAMD_TIMERS")
+];
+
 pub fn main() {
+    // The simulated board replaces both the Arduino core and the C++ shim, see `src/ffi/sim.rs`.
+    if env::var_os("CARGO_FEATURE_HOST_SIM").is_some() {
+        return;
+    }
+
     let config = Config::new().unwrap();
     let core = config.core();
     if core != "arduino" && !core.ends_with(":arduino") {
@@ -15,7 +31,7 @@ pub fn main() {
           .unwrap();
 
     config.builder()
-          .source("src/ffi/rduino.cpp")
+          .source(shim_source())
           .build("rduino")
           .unwrap();
 
@@ -29,3 +45,21 @@ pub fn main() {
           .generate("src/ffi/rduino.hpp")
           .unwrap();
 }
+
+/// Write a source file that sets the defines of the enabled features and includes the shim.
+fn shim_source() -> PathBuf {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let shim = manifest_dir.join("src/ffi/rduino.cpp");
+
+    let mut source = String::new();
+    for &(feature, define) in SHIM_FEATURES {
+        if env::var_os(feature).is_some() {
+            source.push_str(&format!("#define {}\n", define));
+        }
+    }
+    source.push_str(&format!("#include \"{}\"\n", shim.display().to_string().replace('\\', "/")));
+
+    let path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("rduino.cpp");
+    File::create(&path).and_then(|mut file| file.write_all(source.as_bytes())).unwrap();
+    path
+}
@@ -0,0 +1,631 @@
+//! A minimal executor for cooperative, future-based tasks.
+//!
+//! A future is a computation that can make progress without blocking: every time it is polled it
+//! either completes or returns `Poll::Pending`, after which it is polled again once it has been
+//! woken through its `Waker`. The `Executor` runs several such tasks side by side on a single core
+//! without allocating. Futures for waiting on time, serial ports and pin changes are provided by
+//! this module.
+//!
+//! Futures waiting on a pin change are woken from an interrupt service routine. Other futures
+//! register the event they are waiting for with their waker, e.g. a deadline or data arriving on
+//! a serial port. The executor checks these events itself and only polls a task once one of them
+//! has occurred. While no task is ready, it sleeps towards the next deadline.
+
+use ffi::{self, RduinoSerial};
+use interrupts::{self, InterruptMode, InterruptServiceRoutine, IsrGuard};
+use io::Stream;
+use pins::InterruptPin;
+use serial::Serial;
+use sync::Mutex;
+use sys_common::serial::AsRawSerial;
+use time::{self, Duration, Instant};
+
+use core::{fmt, mem};
+
+/// The maximum number of tasks an `Executor` can run at the same time.
+pub const MAX_TASKS: usize = 8;
+
+/// The maximum number of events that tasks can wait for at the same time.
+///
+/// A task that registers an event while all slots are in use is polled again right away instead.
+pub const MAX_EVENTS: usize = 16;
+
+/// The maximum number of `PinChange` futures that can wait at the same time.
+pub const MAX_PIN_CHANGES: usize = 4;
+
+/// The longest time in microseconds the executor sleeps before checking the serial ports again.
+const MAX_SLEEP_US: u64 = 1000;
+
+static TAKEN: Mutex<bool> = Mutex::new(false);
+
+/// The tasks that have been woken since they were last polled, one bit per task.
+static READY: Mutex<u8> = Mutex::new(0);
+
+#[derive(Clone, Copy)]
+enum Event {
+    Deadline(Instant),
+    Readable(*mut RduinoSerial),
+    Writable(*mut RduinoSerial)
+}
+
+impl Event {
+    fn has_occurred(&self, now: Instant) -> bool {
+        match *self {
+            Event::Deadline(deadline) => now >= deadline,
+            Event::Readable(serial) => unsafe { ffi::rduino_serial_available(serial) > 0 },
+            Event::Writable(serial) => unsafe { ffi::rduino_serial_available_for_write(serial) > 0 }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Registration {
+    task: u8,
+    event: Event
+}
+
+// The serial ports are static objects, so the events can be checked from anywhere.
+unsafe impl Send for Registration {}
+
+/// The events the tasks are waiting for.
+static EVENTS: Mutex<[Option<Registration>; MAX_EVENTS]> = Mutex::new([None; MAX_EVENTS]);
+
+/// Wake all tasks whose events have occurred and return whether any task is ready.
+fn wake_events() -> bool {
+    let now = Instant::now();
+    let mut events = EVENTS.lock();
+    let mut ready = READY.lock();
+    for slot in events.iter_mut() {
+        let task = match *slot {
+            Some(registration) if registration.event.has_occurred(now) => registration.task,
+            _ => continue
+        };
+        *ready |= 1 << task;
+        *slot = None;
+    }
+    *ready != 0
+}
+
+/// Forget all events a task was waiting for, because it is about to be polled again.
+fn clear_events(task: u8) {
+    for slot in EVENTS.lock().iter_mut() {
+        if slot.map_or(false, |registration| registration.task == task) {
+            *slot = None;
+        }
+    }
+}
+
+/// The earliest deadline any task is waiting for.
+fn next_deadline() -> Option<Instant> {
+    EVENTS.lock().iter().filter_map(|slot| {
+        match *slot {
+            Some(Registration { event: Event::Deadline(deadline), .. }) => Some(deadline),
+            _ => None
+        }
+    }).min()
+}
+
+/// The result of polling a future.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum Poll<T> {
+    /// The future has completed with the given value.
+    Ready(T),
+
+    /// The future has not completed yet and will wake its task when it can make progress.
+    Pending
+}
+
+/// A computation that completes at some point in the future.
+pub trait Future {
+    /// The value the future completes with.
+    type Output;
+
+    /// Try to make progress.
+    ///
+    /// If the future can not complete yet, it returns `Poll::Pending` and arranges for the given
+    /// waker to be woken once it can make progress again. A future must not be polled after it
+    /// completed.
+    fn poll(&mut self, waker: &Waker) -> Poll<Self::Output>;
+}
+
+impl<'a, F: Future + ?Sized> Future for &'a mut F {
+    type Output = F::Output;
+
+    #[inline]
+    fn poll(&mut self, waker: &Waker) -> Poll<F::Output> {
+        (**self).poll(waker)
+    }
+}
+
+/// Wakes a task so the executor polls it again.
+///
+/// Wakers can be copied freely and may be used from inside of interrupt service routines.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Waker {
+    task: u8
+}
+
+impl Waker {
+    /// Wake the task.
+    #[inline]
+    pub fn wake(&self) {
+        if (self.task as usize) < MAX_TASKS {
+            *READY.lock() |= 1 << self.task;
+        }
+    }
+
+    /// Wake the task once the given instant has been reached.
+    #[inline]
+    pub fn wake_at(&self, deadline: Instant) {
+        self.register(Event::Deadline(deadline));
+    }
+
+    /// Wake the task once the given serial port has received data.
+    #[inline]
+    pub fn wake_readable(&self, serial: &Serial) {
+        self.register(Event::Readable(serial.as_raw()));
+    }
+
+    /// Wake the task once there is room in the transmit buffer of the given serial port.
+    #[inline]
+    pub fn wake_writable(&self, serial: &Serial) {
+        self.register(Event::Writable(serial.as_raw()));
+    }
+
+    fn register(&self, event: Event) {
+        if self.task as usize >= MAX_TASKS {
+            return;
+        }
+
+        let mut events = EVENTS.lock();
+        match events.iter().position(Option::is_none) {
+            Some(index) => events[index] = Some(Registration { task: self.task, event: event }),
+            // Without a free slot the task is simply polled again.
+            None => self.wake()
+        }
+    }
+}
+
+
+
+/// The executor running tasks.
+///
+/// Tasks are futures that are borrowed by the executor, so they are typically created on the stack
+/// of the `rduino_main!` function. There is only one executor, which can be obtained with `take`.
+pub struct Executor<'a> {
+    tasks: [Option<&'a mut Future<Output = ()>>; MAX_TASKS]
+}
+
+impl<'a> Executor<'a> {
+    /// Take the executor.
+    ///
+    /// Returns `None` if the executor was already taken and not dropped since.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine.
+    pub fn take() -> Option<Executor<'a>> {
+        check_isr!("Executor::take");
+
+        {
+            let mut taken = TAKEN.lock();
+            if *taken {
+                return None;
+            }
+            *taken = true;
+        }
+
+        *READY.lock() = 0;
+        *EVENTS.lock() = [None; MAX_EVENTS];
+        Some(Executor {
+            tasks: [None, None, None, None, None, None, None, None]
+        })
+    }
+
+    /// Add a task to the executor.
+    ///
+    /// The task is polled for the first time on the next round of the executor. Returns `false` if
+    /// the maximum number of tasks has been reached.
+    pub fn spawn(&mut self, task: &'a mut Future<Output = ()>) -> bool {
+        match self.tasks.iter().position(Option::is_none) {
+            Some(index) => {
+                self.tasks[index] = Some(task);
+                Waker { task: index as u8 }.wake();
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Poll every task that has been woken once.
+    ///
+    /// Tasks waiting for an event that has occurred, e.g. a deadline that has passed, are woken
+    /// first. Returns the number of tasks that have not completed yet.
+    pub fn run_ready(&mut self) -> usize {
+        wake_events();
+        let ready = mem::replace(&mut *READY.lock(), 0);
+        for index in 0..MAX_TASKS {
+            if ready & 1 << index == 0 {
+                continue;
+            }
+
+            clear_events(index as u8);
+            let done = match self.tasks[index] {
+                Some(ref mut task) => task.poll(&Waker { task: index as u8 }) == Poll::Ready(()),
+                None => false
+            };
+            if done {
+                self.tasks[index] = None;
+            }
+        }
+        self.tasks.iter().filter(|task| task.is_some()).count()
+    }
+
+    /// Run the tasks until all of them have completed.
+    ///
+    /// While no task is ready, the executor sleeps towards the next deadline in steps of at most a
+    /// millisecond, so serial ports keep being checked. Without a deadline it sleeps a millisecond
+    /// at a time. Interrupts can still wake tasks at any time, but the woken task is only polled
+    /// after the current step.
+    pub fn run(&mut self) {
+        while self.run_ready() > 0 {
+            while !wake_events() {
+                let step = match next_deadline() {
+                    Some(deadline) => {
+                        let remaining = deadline.checked_duration_since(Instant::now())
+                                                .unwrap_or_default();
+                        remaining.as_micros().min(MAX_SLEEP_US)
+                    }
+                    None => MAX_SLEEP_US
+                };
+                time::sleep_microsecs(step as u32);
+            }
+        }
+    }
+}
+
+impl<'a> Drop for Executor<'a> {
+    fn drop(&mut self) {
+        *TAKEN.lock() = false;
+    }
+}
+
+impl<'a> fmt::Debug for Executor<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let tasks = self.tasks.iter().filter(|task| task.is_some()).count();
+        write!(fmt, "Executor {{ tasks: {} }}", tasks)
+    }
+}
+
+impl<'a> !Send for Executor<'a> {}
+
+/// Run a single future to completion, polling it continuously.
+///
+/// This does not need the `Executor` and can be used to wait for a future outside of a task. It
+/// is a busy poll: the future is polled again right away instead of waiting for its waker, which
+/// has no effect here.
+pub fn block_on<F: Future>(mut future: F) -> F::Output {
+    // This waker does not belong to any task, so waking it has no effect.
+    let waker = Waker { task: MAX_TASKS as u8 };
+    loop {
+        if let Poll::Ready(value) = future.poll(&waker) {
+            return value;
+        }
+    }
+}
+
+
+
+/// A future created from a closure.
+///
+/// Created by the `poll_fn` function.
+#[derive(Debug)]
+pub struct PollFn<F> {
+    f: F
+}
+
+impl<T, F: FnMut(&Waker) -> Poll<T>> Future for PollFn<F> {
+    type Output = T;
+
+    #[inline]
+    fn poll(&mut self, waker: &Waker) -> Poll<T> {
+        (self.f)(waker)
+    }
+}
+
+/// Create a future that calls the given closure every time it is polled.
+#[inline]
+pub fn poll_fn<T, F: FnMut(&Waker) -> Poll<T>>(f: F) -> PollFn<F> {
+    PollFn { f: f }
+}
+
+
+
+/// A future that completes at a given instant.
+///
+/// Created by the `delay` and `delay_until` functions.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Delay {
+    deadline: Instant
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    #[inline]
+    fn poll(&mut self, waker: &Waker) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            Poll::Ready(())
+        } else {
+            waker.wake_at(self.deadline);
+            Poll::Pending
+        }
+    }
+}
+
+/// Create a future that completes after the given duration.
+#[inline]
+pub fn delay(duration: Duration) -> Delay {
+    delay_until(Instant::now() + duration)
+}
+
+/// Create a future that completes at the given instant.
+#[inline]
+pub fn delay_until(deadline: Instant) -> Delay {
+    Delay { deadline: deadline }
+}
+
+
+
+/// A future that reads a byte from a serial port.
+///
+/// Created by the `read_byte` function.
+#[derive(Debug)]
+pub struct ReadByte<'a> {
+    serial: &'a Serial
+}
+
+impl<'a> Future for ReadByte<'a> {
+    type Output = u8;
+
+    #[inline]
+    fn poll(&mut self, waker: &Waker) -> Poll<u8> {
+        match self.serial.read_byte() {
+            Some(byte) => Poll::Ready(byte),
+            None => {
+                waker.wake_readable(self.serial);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Create a future that reads a byte from the given serial port.
+#[inline]
+pub fn read_byte(serial: &Serial) -> ReadByte {
+    ReadByte { serial: serial }
+}
+
+/// A future that fills a buffer with bytes read from a serial port.
+///
+/// Created by the `read_exact` function.
+#[derive(Debug)]
+pub struct ReadExact<'a> {
+    serial: &'a Serial,
+    buf: &'a mut [u8],
+    pos: usize
+}
+
+impl<'a> Future for ReadExact<'a> {
+    type Output = ();
+
+    fn poll(&mut self, waker: &Waker) -> Poll<()> {
+        let available = self.serial.available().min(self.buf.len() - self.pos);
+        if available > 0 {
+            let end = self.pos + available;
+            self.pos += self.serial.read_bytes(&mut self.buf[self.pos..end]);
+        }
+
+        if self.pos == self.buf.len() {
+            Poll::Ready(())
+        } else {
+            waker.wake_readable(self.serial);
+            Poll::Pending
+        }
+    }
+}
+
+/// Create a future that fills the given buffer with bytes read from the given serial port.
+#[inline]
+pub fn read_exact<'a>(serial: &'a Serial, buf: &'a mut [u8]) -> ReadExact<'a> {
+    ReadExact { serial: serial, buf: buf, pos: 0 }
+}
+
+/// A future that writes all bytes in a buffer to a serial port.
+///
+/// Created by the `write_all` function.
+#[derive(Debug)]
+pub struct WriteAll<'a> {
+    serial: &'a Serial,
+    buf: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Future for WriteAll<'a> {
+    type Output = ();
+
+    fn poll(&mut self, waker: &Waker) -> Poll<()> {
+        let available = self.serial.available_for_write().min(self.buf.len() - self.pos);
+        if available > 0 {
+            let end = self.pos + available;
+            self.pos += self.serial.write_bytes(&self.buf[self.pos..end]);
+        }
+
+        if self.pos == self.buf.len() {
+            Poll::Ready(())
+        } else {
+            waker.wake_writable(self.serial);
+            Poll::Pending
+        }
+    }
+}
+
+/// Create a future that writes all bytes in the given buffer to the given serial port.
+///
+/// Only as many bytes are written at a time as fit in the transmit buffer, so the write never
+/// blocks.
+#[inline]
+pub fn write_all<'a>(serial: &'a Serial, buf: &'a [u8]) -> WriteAll<'a> {
+    WriteAll { serial: serial, buf: buf, pos: 0 }
+}
+
+
+
+#[derive(Clone, Copy)]
+struct PinChangeSlot {
+    used: bool,
+    fired: bool,
+    waker: Option<Waker>
+}
+
+const EMPTY_SLOT: PinChangeSlot = PinChangeSlot {
+    used: false,
+    fired: false,
+    waker: None
+};
+
+static PIN_CHANGE_SLOTS: Mutex<[PinChangeSlot; MAX_PIN_CHANGES]> =
+    Mutex::new([EMPTY_SLOT; MAX_PIN_CHANGES]);
+
+/// The tasks waiting for a free pin change slot, one bit per task.
+static PIN_CHANGE_WAITERS: Mutex<u8> = Mutex::new(0);
+
+fn wake_pin_change(slot: usize) {
+    let mut slots = PIN_CHANGE_SLOTS.lock();
+    let slot = &mut slots[slot];
+    if slot.used {
+        slot.fired = true;
+        if let Some(waker) = slot.waker {
+            waker.wake();
+        }
+    }
+}
+
+macro_rules! pin_change_routines {
+    ($($routine:ident => $slot:expr),*) => {
+        $(
+            extern "C" fn $routine() {
+                interrupts::__enter_isr();
+                wake_pin_change($slot);
+                interrupts::__exit_isr();
+            }
+        )*
+
+        static PIN_CHANGE_ROUTINES: [extern "C" fn(); MAX_PIN_CHANGES] = [$($routine),*];
+    }
+}
+
+pin_change_routines! {
+    pin_change_0 => 0,
+    pin_change_1 => 1,
+    pin_change_2 => 2,
+    pin_change_3 => 3
+}
+
+/// A future that completes when the level of a pin changes.
+///
+/// Created by the `pin_change` function. While the future is waiting, an interrupt service routine
+/// that wakes it is attached to the pin's interrupt, replacing any routine attached before. The
+/// routine is detached again when the future completes or is dropped.
+#[derive(Debug)]
+pub struct PinChange<'a, PULL: 'a> {
+    pin: &'a InterruptPin<PULL>,
+    mode: InterruptMode,
+    slot: Option<usize>,
+    guard: Option<IsrGuard<'a>>
+}
+
+impl<'a, PULL: 'a> PinChange<'a, PULL> {
+    fn release(&mut self) {
+        // Detach the routine first, so it can not wake a slot that is being reused.
+        self.guard = None;
+        if let Some(slot) = self.slot.take() {
+            PIN_CHANGE_SLOTS.lock()[slot] = EMPTY_SLOT;
+            let waiters = mem::replace(&mut *PIN_CHANGE_WAITERS.lock(), 0);
+            *READY.lock() |= waiters;
+        }
+    }
+}
+
+impl<'a, PULL: 'a> Future for PinChange<'a, PULL> {
+    type Output = ();
+
+    fn poll(&mut self, waker: &Waker) -> Poll<()> {
+        match self.slot {
+            Some(slot) => {
+                {
+                    let mut slots = PIN_CHANGE_SLOTS.lock();
+                    if !slots[slot].fired {
+                        slots[slot].waker = Some(*waker);
+                        return Poll::Pending;
+                    }
+                }
+                self.release();
+                Poll::Ready(())
+            }
+            None => {
+                let slot = {
+                    let mut slots = PIN_CHANGE_SLOTS.lock();
+                    let slot = slots.iter().position(|slot| !slot.used);
+                    if let Some(slot) = slot {
+                        slots[slot] = PinChangeSlot {
+                            used: true,
+                            fired: false,
+                            waker: Some(*waker)
+                        };
+                    }
+                    slot
+                };
+
+                match slot {
+                    Some(slot) => {
+                        let routine = InterruptServiceRoutine::__new(PIN_CHANGE_ROUTINES[slot]);
+                        self.slot = Some(slot);
+                        self.guard = Some(routine.attach(self.pin, self.mode)
+                                                 .expect("interrupt pin has no interrupt"));
+                    }
+                    // All slots are in use, try again once one is released.
+                    None if (waker.task as usize) < MAX_TASKS => {
+                        *PIN_CHANGE_WAITERS.lock() |= 1 << waker.task;
+                    }
+                    None => waker.wake()
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<'a, PULL: 'a> Drop for PinChange<'a, PULL> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Create a future that completes when the given pin's interrupt triggers in the given mode.
+///
+/// The `mode` parameter can either be an `InterruptMode` or an `InterruptModeExt` from the
+/// platform-specific extensions if the platform supports it. Up to `MAX_PIN_CHANGES` of these
+/// futures can wait at the same time, any others wait for a free slot.
+///
+/// # Panics
+/// Polling the future panics if it is done from inside an interrupt service routine.
+#[inline]
+pub fn pin_change<'a, PULL, M>(pin: &'a InterruptPin<PULL>, mode: M) -> PinChange<'a, PULL>
+        where M: Into<InterruptMode> {
+    PinChange {
+        pin: pin,
+        mode: mode.into(),
+        slot: None,
+        guard: None
+    }
+}
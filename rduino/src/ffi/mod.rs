@@ -3,4 +3,11 @@
 //! **Warning**: This module should only be used by library developers if at all.
 #![allow(bad_style, missing_docs, unused)]
 
+#[cfg(not(feature = "host-sim"))]
 include!(concat!(env!("OUT_DIR"), "/rduino.rs"));
+
+#[cfg(feature = "host-sim")]
+pub use self::sim::*;
+
+#[cfg(feature = "host-sim")]
+mod sim;
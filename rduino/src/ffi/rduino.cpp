@@ -1,6 +1,10 @@
 #include "rduino.hpp"
 
 #include <Arduino.h>
+#ifdef ARDUINO_ARCH_AVR
+#include <avr/eeprom.h>
+#include <avr/wdt.h>
+#endif
 
 extern "C" {
 
@@ -133,6 +137,15 @@ int rduino_digital_pin_to_interrupt(uint8_t pin) {
     return interrupt;
 }
 
+int rduino_digital_pin_to_pin_change_interrupt(uint8_t pin) {
+#if defined(ARDUINO_ARCH_AVR) && defined(RDUINO_PIN_CHANGE_INTERRUPTS)
+    if (pin < NUM_DIGITAL_PINS && digitalPinToPCICR(pin) != nullptr) {
+        return RDUINO_PIN_CHANGE_INTERRUPT_BASE + pin;
+    }
+#endif
+    return -1;
+}
+
 // Digital I/O
 void rduino_pin_mode(uint8_t pin, RduinoPinMode mode) {
     unsigned int real_mode;
@@ -165,6 +178,23 @@ RduinoPinLevel rduino_digital_read(uint8_t pin) {
     return digitalRead(pin) == HIGH ? RduinoPinLevel::High : RduinoPinLevel::Low;
 }
 
+RduinoPinLevel rduino_digital_read_output(uint8_t pin) {
+    bool high;
+#if defined(ARDUINO_ARCH_AVR)
+    uint8_t port = digitalPinToPort(pin);
+    high = port != NOT_A_PIN && (*portOutputRegister(port) & digitalPinToBitMask(pin));
+#elif defined(ARDUINO_ARCH_SAMD)
+    const PinDescription &description = g_APinDescription[pin];
+    high = PORT->Group[description.ulPort].OUT.reg & (1ul << description.ulPin);
+#elif defined(ARDUINO_ARCH_SAM)
+    const PinDescription &description = g_APinDescription[pin];
+    high = description.pPort->PIO_ODSR & description.ulPin;
+#else
+    high = digitalRead(pin) == HIGH;
+#endif
+    return high ? RduinoPinLevel::High : RduinoPinLevel::Low;
+}
+
 
 // Analog I/O
 #ifdef ARDUINO_ARCH_AVR
@@ -303,15 +333,109 @@ void rduino_random_seed(unsigned long seed) {
     randomSeed(seed);
 }
 
-void rduino_random(long min, long max) {
-    random(min, max);
+long rduino_random(long min, long max) {
+    return random(min, max);
 }
 
 
 // External interrupts
 typedef void (* RduinoIsr)(void);
 
-void rduino_attach_interrupt(uint8_t interrupt, RduinoIsr isr, RduinoInterruptMode mode) {
+// The SoftwareSerial library defines the pin change interrupt vectors as well, so they are only
+// defined with the `pin-change-interrupts` feature.
+#if defined(ARDUINO_ARCH_AVR) && defined(RDUINO_PIN_CHANGE_INTERRUPTS)
+// Every pin change interrupt vector serves a group of up to 8 pins. The vectors remember the last
+// level of every pin with an ISR, so they can tell which of the pins in the group changed.
+static volatile RduinoIsr rduino_pin_change_isrs[NUM_DIGITAL_PINS];
+static volatile bool rduino_pin_change_levels[NUM_DIGITAL_PINS];
+
+static bool rduino_pin_level(uint8_t pin) {
+    return (*portInputRegister(digitalPinToPort(pin)) & digitalPinToBitMask(pin)) != 0;
+}
+
+static void rduino_pin_change_dispatch(uint8_t group) {
+    for (uint8_t pin = 0; pin < NUM_DIGITAL_PINS; pin++) {
+        RduinoIsr isr = rduino_pin_change_isrs[pin];
+        if (isr == nullptr || digitalPinToPCICRbit(pin) != group) {
+            continue;
+        }
+
+        bool level = rduino_pin_level(pin);
+        if (level != rduino_pin_change_levels[pin]) {
+            rduino_pin_change_levels[pin] = level;
+            isr();
+        }
+    }
+}
+
+#ifdef PCINT0_vect
+ISR(PCINT0_vect) {
+    rduino_pin_change_dispatch(0);
+}
+#endif
+
+#ifdef PCINT1_vect
+ISR(PCINT1_vect) {
+    rduino_pin_change_dispatch(1);
+}
+#endif
+
+#ifdef PCINT2_vect
+ISR(PCINT2_vect) {
+    rduino_pin_change_dispatch(2);
+}
+#endif
+
+#ifdef PCINT3_vect
+ISR(PCINT3_vect) {
+    rduino_pin_change_dispatch(3);
+}
+#endif
+
+static bool rduino_attach_pin_change_interrupt(uint8_t pin, RduinoIsr isr,
+                                              RduinoInterruptMode mode) {
+    if (pin >= NUM_DIGITAL_PINS || digitalPinToPCICR(pin) == nullptr
+            || mode != RduinoInterruptMode::Change) {
+        return false;
+    }
+
+    uint8_t sreg = SREG;
+    cli();
+    rduino_pin_change_isrs[pin] = isr;
+    rduino_pin_change_levels[pin] = rduino_pin_level(pin);
+    *digitalPinToPCMSK(pin) |= _BV(digitalPinToPCMSKbit(pin));
+    *digitalPinToPCICR(pin) |= _BV(digitalPinToPCICRbit(pin));
+    SREG = sreg;
+    return true;
+}
+
+static void rduino_detach_pin_change_interrupt(uint8_t pin) {
+    if (pin >= NUM_DIGITAL_PINS || digitalPinToPCICR(pin) == nullptr) {
+        return;
+    }
+
+    uint8_t sreg = SREG;
+    cli();
+    volatile uint8_t *mask = digitalPinToPCMSK(pin);
+    *mask &= ~_BV(digitalPinToPCMSKbit(pin));
+    if (*mask == 0) {
+        *digitalPinToPCICR(pin) &= ~_BV(digitalPinToPCICRbit(pin));
+    }
+    rduino_pin_change_isrs[pin] = nullptr;
+    SREG = sreg;
+}
+#endif
+
+bool rduino_attach_interrupt(uint8_t interrupt, RduinoIsr isr, RduinoInterruptMode mode) {
+    if (interrupt >= RDUINO_PIN_CHANGE_INTERRUPT_BASE) {
+#if defined(ARDUINO_ARCH_AVR) && defined(RDUINO_PIN_CHANGE_INTERRUPTS)
+        return rduino_attach_pin_change_interrupt(interrupt - RDUINO_PIN_CHANGE_INTERRUPT_BASE, isr,
+                                                  mode);
+#else
+        return false;
+#endif
+    }
+
     int real_mode;
     switch (mode) {
         case RduinoInterruptMode::Low:
@@ -332,16 +456,246 @@ void rduino_attach_interrupt(uint8_t interrupt, RduinoIsr isr, RduinoInterruptMo
             break;
 #endif
         default:
-            return;
+            return false;
     }
     attachInterrupt(interrupt, isr, real_mode);
+    return true;
 }
 
 void rduino_detach_interrupt(uint8_t interrupt) {
+    if (interrupt >= RDUINO_PIN_CHANGE_INTERRUPT_BASE) {
+#if defined(ARDUINO_ARCH_AVR) && defined(RDUINO_PIN_CHANGE_INTERRUPTS)
+        rduino_detach_pin_change_interrupt(interrupt - RDUINO_PIN_CHANGE_INTERRUPT_BASE);
+#endif
+        return;
+    }
+
     detachInterrupt(interrupt);
 }
 
 
+// Timer interrupts
+static volatile RduinoIsr rduino_timer_isrs[4];
+
+#if defined(ARDUINO_ARCH_AVR)
+// The compare match A vectors are already defined by the `tone()` implementation and the Servo
+// library. The timers count up to OCRnA, but the interrupt is generated by compare match B, which
+// is set to the same value.
+#ifdef TIMSK1
+ISR(TIMER1_COMPB_vect) {
+    RduinoIsr isr = rduino_timer_isrs[static_cast<uint8_t>(RduinoTimer::Timer1)];
+    if (isr) {
+        isr();
+    }
+}
+
+static bool rduino_timer1_start(uint32_t frequency) {
+    static const uint16_t PRESCALERS[] = { 1, 8, 64, 256, 1024 };
+
+    // Dividing in two steps gives the same result as dividing by the product, which can overflow.
+    for (uint8_t i = 0; i < sizeof(PRESCALERS) / sizeof(PRESCALERS[0]); i++) {
+        uint32_t top = F_CPU / PRESCALERS[i] / frequency;
+        if (top == 0) {
+            return false;
+        }
+        if (top <= 0x10000) {
+            uint8_t sreg = SREG;
+            cli();
+            TCCR1A = 0;
+            TCCR1B = 0;
+            TCNT1 = 0;
+            OCR1A = top - 1;
+            OCR1B = top - 1;
+            TIFR1 = _BV(OCF1B);
+            TIMSK1 = _BV(OCIE1B);
+            // CTC mode, the clock select bits equal the prescaler index plus one.
+            TCCR1B = _BV(WGM12) | (i + 1);
+            SREG = sreg;
+            return true;
+        }
+    }
+    return false;
+}
+
+static void rduino_timer1_stop() {
+    TIMSK1 = 0;
+    TCCR1B = 0;
+}
+#endif
+
+#ifdef TIMSK2
+ISR(TIMER2_COMPB_vect) {
+    RduinoIsr isr = rduino_timer_isrs[static_cast<uint8_t>(RduinoTimer::Timer2)];
+    if (isr) {
+        isr();
+    }
+}
+
+static bool rduino_timer2_start(uint32_t frequency) {
+    static const uint16_t PRESCALERS[] = { 1, 8, 32, 64, 128, 256, 1024 };
+
+    for (uint8_t i = 0; i < sizeof(PRESCALERS) / sizeof(PRESCALERS[0]); i++) {
+        uint32_t top = F_CPU / PRESCALERS[i] / frequency;
+        if (top == 0) {
+            return false;
+        }
+        if (top <= 0x100) {
+            uint8_t sreg = SREG;
+            cli();
+            TCCR2A = _BV(WGM21);
+            TCCR2B = 0;
+            TCNT2 = 0;
+            OCR2A = top - 1;
+            OCR2B = top - 1;
+            TIFR2 = _BV(OCF2B);
+            TIMSK2 = _BV(OCIE2B);
+            TCCR2B = i + 1;
+            SREG = sreg;
+            return true;
+        }
+    }
+    return false;
+}
+
+static void rduino_timer2_stop() {
+    TIMSK2 = 0;
+    TCCR2B = 0;
+}
+#endif
+
+#elif defined(ARDUINO_ARCH_SAMD)
+static void rduino_tc_sync(TcCount16 *counter) {
+    while (counter->STATUS.bit.SYNCBUSY);
+}
+
+static bool rduino_tc_start(Tc *tc, uint16_t clock_id, IRQn_Type irq, uint32_t frequency) {
+    static const uint16_t PRESCALERS[] = { 1, 2, 4, 8, 16, 64, 256, 1024 };
+
+    for (uint8_t i = 0; i < sizeof(PRESCALERS) / sizeof(PRESCALERS[0]); i++) {
+        uint32_t top = SystemCoreClock / PRESCALERS[i] / frequency;
+        if (top == 0) {
+            return false;
+        }
+        if (top <= 0x10000) {
+            GCLK->CLKCTRL.reg = (uint16_t) (GCLK_CLKCTRL_CLKEN | GCLK_CLKCTRL_GEN_GCLK0 | clock_id);
+            while (GCLK->STATUS.bit.SYNCBUSY);
+
+            TcCount16 *counter = &tc->COUNT16;
+            counter->CTRLA.reg &= ~TC_CTRLA_ENABLE;
+            rduino_tc_sync(counter);
+            counter->CTRLA.reg = TC_CTRLA_SWRST;
+            while (counter->CTRLA.bit.SWRST);
+
+            counter->CTRLA.reg = TC_CTRLA_MODE_COUNT16 | TC_CTRLA_WAVEGEN_MFRQ
+                               | TC_CTRLA_PRESCALER(i);
+            rduino_tc_sync(counter);
+            counter->CC[0].reg = (uint16_t) (top - 1);
+            rduino_tc_sync(counter);
+
+            counter->INTFLAG.reg = TC_INTFLAG_MC0;
+            counter->INTENSET.reg = TC_INTENSET_MC0;
+            NVIC_ClearPendingIRQ(irq);
+            NVIC_EnableIRQ(irq);
+
+            counter->CTRLA.reg |= TC_CTRLA_ENABLE;
+            rduino_tc_sync(counter);
+            return true;
+        }
+    }
+    return false;
+}
+
+static void rduino_tc_stop(Tc *tc, IRQn_Type irq) {
+    TcCount16 *counter = &tc->COUNT16;
+    NVIC_DisableIRQ(irq);
+    counter->INTENCLR.reg = TC_INTENCLR_MC0;
+    counter->CTRLA.reg &= ~TC_CTRLA_ENABLE;
+    rduino_tc_sync(counter);
+}
+
+// The Servo library defines `TC4_Handler` as well and some cores use TC3 or TC4 for `tone()`, so
+// the handlers and with them the timers are only available with the `samd-timers` feature.
+#ifdef RDUINO_SAMD_TIMERS
+void TC3_Handler(void) {
+    TC3->COUNT16.INTFLAG.reg = TC_INTFLAG_MC0;
+    RduinoIsr isr = rduino_timer_isrs[static_cast<uint8_t>(RduinoTimer::Tc3)];
+    if (isr) {
+        isr();
+    }
+}
+
+void TC4_Handler(void) {
+    TC4->COUNT16.INTFLAG.reg = TC_INTFLAG_MC0;
+    RduinoIsr isr = rduino_timer_isrs[static_cast<uint8_t>(RduinoTimer::Tc4)];
+    if (isr) {
+        isr();
+    }
+}
+#endif
+#endif
+
+static bool rduino_timer_start(RduinoTimer timer, uint32_t frequency) {
+    switch (timer) {
+#if defined(ARDUINO_ARCH_AVR) && defined(TIMSK1)
+        case RduinoTimer::Timer1:
+            return rduino_timer1_start(frequency);
+#endif
+#if defined(ARDUINO_ARCH_AVR) && defined(TIMSK2)
+        case RduinoTimer::Timer2:
+            return rduino_timer2_start(frequency);
+#endif
+#if defined(ARDUINO_ARCH_SAMD) && defined(RDUINO_SAMD_TIMERS)
+        case RduinoTimer::Tc3:
+            return rduino_tc_start(TC3, GCLK_CLKCTRL_ID_TCC2_TC3, TC3_IRQn, frequency);
+        case RduinoTimer::Tc4:
+            return rduino_tc_start(TC4, GCLK_CLKCTRL_ID_TC4_TC5, TC4_IRQn, frequency);
+#endif
+        default:
+            return false;
+    }
+}
+
+bool rduino_timer_attach(RduinoTimer timer, uint32_t frequency, RduinoIsr isr) {
+    if (frequency == 0) {
+        return false;
+    }
+
+    rduino_timer_detach(timer);
+    rduino_timer_isrs[static_cast<uint8_t>(timer)] = isr;
+    if (!rduino_timer_start(timer, frequency)) {
+        rduino_timer_isrs[static_cast<uint8_t>(timer)] = nullptr;
+        return false;
+    }
+    return true;
+}
+
+void rduino_timer_detach(RduinoTimer timer) {
+    switch (timer) {
+#if defined(ARDUINO_ARCH_AVR) && defined(TIMSK1)
+        case RduinoTimer::Timer1:
+            rduino_timer1_stop();
+            break;
+#endif
+#if defined(ARDUINO_ARCH_AVR) && defined(TIMSK2)
+        case RduinoTimer::Timer2:
+            rduino_timer2_stop();
+            break;
+#endif
+#if defined(ARDUINO_ARCH_SAMD) && defined(RDUINO_SAMD_TIMERS)
+        case RduinoTimer::Tc3:
+            rduino_tc_stop(TC3, TC3_IRQn);
+            break;
+        case RduinoTimer::Tc4:
+            rduino_tc_stop(TC4, TC4_IRQn);
+            break;
+#endif
+        default:
+            return;
+    }
+    rduino_timer_isrs[static_cast<uint8_t>(timer)] = nullptr;
+}
+
+
 // Communication
 #if defined(ARDUINO_ARCH_SAMD)
 typedef Uart RduinoSerialHardware;
@@ -696,4 +1050,58 @@ void rduino_serial_flush(RduinoSerial *serial) {
     serial->stream()->flush();
 }
 
+bool rduino_serial_can_write_polled(RduinoSerial *serial) {
+#ifdef ARDUINO_ARCH_AVR
+    // HardwareSerial polls the data register itself when its buffer fills up or is flushed while
+    // interrupts are disabled. The SAMD cores and the USB ports rely on interrupts throughout.
+    return serial->type == RduinoSerial::Type::Uart;
+#else
+    return false;
+#endif
+}
+
+bool rduino_serial_write_polled(RduinoSerial *serial, const uint8_t *buffer, size_t length) {
+    if (!rduino_serial_can_write_polled(serial)) {
+        return false;
+    }
+#ifdef ARDUINO_ARCH_AVR
+    serial->hw->write(buffer, length);
+    serial->hw->flush();
+#endif
+    return true;
+}
+
+
+// EEPROM
+size_t rduino_eeprom_length() {
+#ifdef ARDUINO_ARCH_AVR
+    return E2END + 1;
+#else
+    return 0;
+#endif
+}
+
+void rduino_eeprom_read(uint16_t address, uint8_t *buffer, size_t length) {
+#ifdef ARDUINO_ARCH_AVR
+    eeprom_read_block(buffer, reinterpret_cast<const void *>(address), length);
+#endif
+}
+
+void rduino_eeprom_write(uint16_t address, const uint8_t *buffer, size_t length) {
+#ifdef ARDUINO_ARCH_AVR
+    // Only the bytes that differ are written, to spare the EEPROM.
+    eeprom_update_block(buffer, reinterpret_cast<void *>(address), length);
+#endif
+}
+
+// Board
+void rduino_reset() {
+#ifdef ARDUINO_ARCH_AVR
+    wdt_enable(WDTO_15MS);
+#else
+    NVIC_SystemReset();
+#endif
+    for (;;) {}
+}
+
 }
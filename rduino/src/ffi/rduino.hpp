@@ -37,8 +37,12 @@ extern "C" {
 extern const uint8_t RDUINO_ANALOG_INPUT_PINS[RDUINO_NUM_ANALOG_INPUTS];
 extern const uint8_t RDUINO_ANALOG_OUTPUT_PINS[RDUINO_NUM_ANALOG_OUTPUTS];
 
+// Pin change interrupts are numbered after the external interrupts, starting at this number.
+#define RDUINO_PIN_CHANGE_INTERRUPT_BASE 128
+
 bool rduino_digital_pin_has_pwm(uint8_t pin);
 int rduino_digital_pin_to_interrupt(uint8_t pin);
+int rduino_digital_pin_to_pin_change_interrupt(uint8_t pin);
 
 // Digital I/O
 enum class RduinoPinLevel {
@@ -58,6 +62,8 @@ enum class RduinoPinMode {
 void rduino_pin_mode(uint8_t pin, RduinoPinMode mode);
 void rduino_digital_write(uint8_t pin, RduinoPinLevel value);
 RduinoPinLevel rduino_digital_read(uint8_t pin);
+// The level an output pin is set to, which differs from the level read when a load overpowers it.
+RduinoPinLevel rduino_digital_read_output(uint8_t pin);
 
 // Analog I/O
 enum class RduinoAnalogReference {
@@ -102,7 +108,7 @@ void rduino_delay_microseconds(unsigned int us);
 
 // Random
 void rduino_random_seed(unsigned long seed);
-void rduino_random(long min, long max);
+long rduino_random(long min, long max);
 
 // External interrupts
 enum class RduinoInterruptMode {
@@ -117,9 +123,23 @@ enum class RduinoInterruptMode {
 
 typedef void(* RduinoIsr)(void);
 
-void rduino_attach_interrupt(uint8_t interrupt, RduinoIsr isr, RduinoInterruptMode mode);
+bool rduino_attach_interrupt(uint8_t interrupt, RduinoIsr isr, RduinoInterruptMode mode);
 void rduino_detach_interrupt(uint8_t interrupt);
 
+// Timer interrupts
+enum class RduinoTimer {
+    // AVR only
+    Timer1,
+    Timer2,
+
+    // SAMD only
+    Tc3,
+    Tc4
+};
+
+bool rduino_timer_attach(RduinoTimer timer, uint32_t frequency, RduinoIsr isr);
+void rduino_timer_detach(RduinoTimer timer);
+
 // Communication
 struct RduinoSerial;
 
@@ -172,5 +192,19 @@ size_t rduino_serial_available_for_write(RduinoSerial *serial);
 bool rduino_serial_write(RduinoSerial *serial, uint8_t value);
 size_t rduino_serial_write_bytes(RduinoSerial *serial, const uint8_t *buffer, size_t length);
 void rduino_serial_flush(RduinoSerial *serial);
+// Whether the port can transmit without relying on interrupts.
+bool rduino_serial_can_write_polled(RduinoSerial *serial);
+// Writes without relying on interrupts, so it can be used while they are disabled. Returns false
+// if the port can not transmit without them.
+bool rduino_serial_write_polled(RduinoSerial *serial, const uint8_t *buffer, size_t length);
+
+// EEPROM
+// The size of the EEPROM in bytes, or zero if the board has none.
+size_t rduino_eeprom_length();
+void rduino_eeprom_read(uint16_t address, uint8_t *buffer, size_t length);
+void rduino_eeprom_write(uint16_t address, const uint8_t *buffer, size_t length);
+
+// Board
+void rduino_reset();
 
 }
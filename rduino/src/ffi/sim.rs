@@ -0,0 +1,454 @@
+// Pure Rust replacement for `rduino.hpp`/`rduino.cpp` backed by the simulated board.
+//
+// The declarations mirror the ones generated by bindgen, so the rest of the crate does not need to
+// know which backend it is built against.
+
+use platform::raw::{c_int, c_long, c_uint, c_ulong};
+use sys::board::{self, Isr, TimerState, ToneState, ANALOG_INPUT_PINS, CYCLES_PER_MICRO,
+                 EEPROM_SIZE, NUM_DIGITAL_PINS, NUM_TIMERS, PIN_CHANGE_INTERRUPT_BASE, PWM_PINS,
+                 SERIAL_BUFFER_SIZE};
+
+use core::{ptr, slice};
+
+// Pins
+pub const RDUINO_NUM_DIGITAL_PINS: u32 = NUM_DIGITAL_PINS as u32;
+pub const RDUINO_NUM_ANALOG_INPUTS: u32 = 6;
+pub const RDUINO_NUM_ANALOG_OUTPUTS: u32 = 0;
+
+pub static RDUINO_ANALOG_INPUT_PINS: [u8; 6] = ANALOG_INPUT_PINS;
+pub static RDUINO_ANALOG_OUTPUT_PINS: [u8; 0] = [];
+
+pub const RDUINO_PIN_CHANGE_INTERRUPT_BASE: u32 = PIN_CHANGE_INTERRUPT_BASE as u32;
+
+pub unsafe fn rduino_digital_pin_has_pwm(pin: u8) -> bool {
+    PWM_PINS.contains(&pin)
+}
+
+pub unsafe fn rduino_digital_pin_to_interrupt(pin: u8) -> c_int {
+    board::interrupt_for_pin(pin).map_or(-1, |interrupt| interrupt as c_int)
+}
+
+pub unsafe fn rduino_digital_pin_to_pin_change_interrupt(pin: u8) -> c_int {
+    if (pin as usize) < NUM_DIGITAL_PINS {
+        (PIN_CHANGE_INTERRUPT_BASE + pin as usize) as c_int
+    } else {
+        -1
+    }
+}
+
+// Digital I/O
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RduinoPinLevel {
+    Low = 0,
+    High = 1
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RduinoPinMode {
+    Input = 0,
+    InputPullup = 1,
+    Output = 2,
+    InputPulldown = 3
+}
+
+pub unsafe fn rduino_pin_mode(pin: u8, mode: RduinoPinMode) {
+    board::update_pin(pin, |state| state.mode = Some(mode));
+}
+
+pub unsafe fn rduino_digital_write(pin: u8, value: RduinoPinLevel) {
+    board::update_pin(pin, |state| state.output = value);
+}
+
+pub unsafe fn rduino_digital_read(pin: u8) -> RduinoPinLevel {
+    board::with_board(|board| board.pins[pin as usize].level())
+}
+
+pub unsafe fn rduino_digital_read_output(pin: u8) -> RduinoPinLevel {
+    board::with_board(|board| board.pins[pin as usize].output)
+}
+
+// Analog I/O
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RduinoAnalogReference {
+    Default = 0,
+    External = 1,
+    Internal = 2,
+    Internal1v1 = 3,
+    Internal2v56 = 4,
+    Internal1v0 = 5,
+    Internal1v65 = 6,
+    Internal2v23 = 7
+}
+
+pub unsafe fn rduino_analog_reference(type_: RduinoAnalogReference) {
+    board::with_board(|board| board.analog_reference = type_);
+}
+
+pub unsafe fn rduino_analog_read(pin: u8) -> u16 {
+    board::with_board(|board| {
+        let max = (1u32 << board.analog_read_resolution) - 1;
+        board.pins[pin as usize].analog_input.min(max as u16)
+    })
+}
+
+pub unsafe fn rduino_analog_write(pin: u8, value: u16) {
+    board::update_pin(pin, |state| {
+        state.mode = Some(RduinoPinMode::Output);
+        state.analog_output = Some(value);
+    });
+}
+
+pub unsafe fn rduino_analog_read_resolution(res: u8) {
+    board::with_board(|board| board.analog_read_resolution = res);
+}
+
+pub unsafe fn rduino_analog_write_resolution(res: u8) {
+    board::with_board(|board| board.analog_write_resolution = res);
+}
+
+// Advanced I/O
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RduinoBitOrder {
+    MsbFirst = 0,
+    LsbFirst = 1
+}
+
+pub unsafe fn rduino_tone(pin: u8, frequency: c_uint, duration: c_ulong) {
+    board::update_pin(pin, |state| state.mode = Some(RduinoPinMode::Output));
+    board::with_board(|board| {
+        let end = if duration > 0 {
+            Some(board.micros + duration as u64 * 1000)
+        } else {
+            None
+        };
+        board.tone = Some(ToneState { pin: pin, frequency: frequency as u32, end: end });
+    });
+}
+
+pub unsafe fn rduino_no_tone(pin: u8) {
+    board::with_board(|board| {
+        if board.tone.map_or(false, |tone| tone.pin == pin) {
+            board.tone = None;
+        }
+    });
+    board::update_pin(pin, |state| state.output = RduinoPinLevel::Low);
+}
+
+pub unsafe fn rduino_shift_out(data_pin: u8, _clock_pin: u8, bit_order: RduinoBitOrder, value: u8) {
+    board::with_board(|board| {
+        board.shifted_out.push((data_pin, board::bit_order_value(bit_order, value)));
+    });
+}
+
+pub unsafe fn rduino_shift_in(data_pin: u8, clock_pin: u8, bit_order: RduinoBitOrder) -> u8 {
+    board::with_board(|board| {
+        // Like `shiftIn()`, the clock is raised before each bit is read and lowered after. The
+        // register shifts on the rising edge, so the first bit is lost unless the clock was high.
+        let mut value = 0;
+        for bit in 0..8 {
+            if bit > 0 || board.pins[clock_pin as usize].output == RduinoPinLevel::Low {
+                if let Some(index) = board.shift_in.iter().position(|&(pin, _)| pin == data_pin) {
+                    board.shift_in.remove(index);
+                }
+            }
+            let front = board.shift_in.iter().find(|&&(pin, _)| pin == data_pin);
+            if front.map_or(false, |&(_, set)| set) {
+                value |= 0x80 >> bit;
+            }
+        }
+        board.pins[clock_pin as usize].output = RduinoPinLevel::Low;
+        board::bit_order_value(bit_order, value)
+    })
+}
+
+pub unsafe fn rduino_pulse_in(pin: u8, value: RduinoPinLevel, timeout: c_ulong) -> c_ulong {
+    let width = board::with_board(|board| {
+        let index = board.pulses.iter().position(|&(pulse_pin, level, width)| {
+            pulse_pin == pin && level == value && width <= timeout
+        });
+        index.map(|index| board.pulses.remove(index).unwrap().2)
+    });
+
+    // Timers keep running while the pulse is measured.
+    board::advance(width.unwrap_or(timeout) as u64);
+    width.unwrap_or(0)
+}
+
+// Time
+pub unsafe fn rduino_millis() -> c_ulong {
+    board::with_board(|board| (board.micros / 1000) as c_ulong)
+}
+
+pub unsafe fn rduino_micros() -> c_ulong {
+    board::with_board(|board| board.micros as c_ulong)
+}
+
+pub unsafe fn rduino_delay(ms: c_ulong) {
+    board::advance(ms as u64 * 1000);
+}
+
+pub unsafe fn rduino_delay_microseconds(us: c_uint) {
+    board::advance(us as u64);
+}
+
+// Random
+pub unsafe fn rduino_random_seed(seed: c_ulong) {
+    if seed != 0 {
+        board::with_board(|board| board.random_seed = seed);
+    }
+}
+
+pub unsafe fn rduino_random(min: c_long, max: c_long) -> c_long {
+    board::with_board(|board| board.random(min, max))
+}
+
+// External interrupts
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RduinoInterruptMode {
+    Low = 0,
+    Change = 1,
+    Rising = 2,
+    Falling = 3,
+    High = 4
+}
+
+pub type RduinoIsr = Option<unsafe extern "C" fn()>;
+
+pub unsafe fn rduino_attach_interrupt(interrupt: u8, isr: RduinoIsr,
+                                      mode: RduinoInterruptMode) -> bool {
+    // Like `attachInterrupt`, attaching to an external interrupt that does not exist is ignored.
+    if interrupt as usize >= PIN_CHANGE_INTERRUPT_BASE && mode != RduinoInterruptMode::Change {
+        return false;
+    }
+
+    match board::isr_index(interrupt) {
+        Some(index) => {
+            board::with_board(|board| {
+                board.isrs[index] = isr.map(|routine| Isr { routine: routine, mode: mode });
+                board.pending[index] = false;
+            });
+            true
+        }
+        None => (interrupt as usize) < PIN_CHANGE_INTERRUPT_BASE
+    }
+}
+
+pub unsafe fn rduino_detach_interrupt(interrupt: u8) {
+    if let Some(index) = board::isr_index(interrupt) {
+        board::with_board(|board| {
+            board.isrs[index] = None;
+            board.pending[index] = false;
+        });
+    }
+}
+
+// Timer interrupts
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RduinoTimer {
+    Timer1 = 0,
+    Timer2 = 1,
+    Tc3 = 2,
+    Tc4 = 3
+}
+
+pub unsafe fn rduino_timer_attach(timer: RduinoTimer, frequency: u32, isr: RduinoIsr) -> bool {
+    let period = match board::timer_period(timer, frequency) {
+        Some(period) => period,
+        None => return false
+    };
+
+    board::with_board(|board| {
+        let now = board.micros * CYCLES_PER_MICRO;
+        board.timers[timer as usize] = isr.map(|routine| {
+            TimerState { routine: routine, period: period, next: now + period, pending: false }
+        });
+    });
+    true
+}
+
+pub unsafe fn rduino_timer_detach(timer: RduinoTimer) {
+    if (timer as usize) < NUM_TIMERS {
+        board::with_board(|board| board.timers[timer as usize] = None);
+    }
+}
+
+// Communication
+#[derive(Debug)]
+pub struct RduinoSerial {
+    port: usize
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RduinoSerialConfig {
+    Serial5N1 = 0,
+    Serial6N1 = 1,
+    Serial7N1 = 2,
+    Serial8N1 = 3,
+    Serial5N2 = 4,
+    Serial6N2 = 5,
+    Serial7N2 = 6,
+    Serial8N2 = 7,
+    Serial5E1 = 8,
+    Serial6E1 = 9,
+    Serial7E1 = 10,
+    Serial8E1 = 11,
+    Serial5E2 = 12,
+    Serial6E2 = 13,
+    Serial7E2 = 14,
+    Serial8E2 = 15,
+    Serial5O1 = 16,
+    Serial6O1 = 17,
+    Serial7O1 = 18,
+    Serial8O1 = 19,
+    Serial5O2 = 20,
+    Serial6O2 = 21,
+    Serial7O2 = 22,
+    Serial8O2 = 23
+}
+
+static SERIALS: [RduinoSerial; 2] = [RduinoSerial { port: 0 }, RduinoSerial { port: 1 }];
+
+#[inline]
+fn serial(port: usize) -> *mut RduinoSerial {
+    &SERIALS[port] as *const RduinoSerial as *mut RduinoSerial
+}
+
+pub unsafe fn rduino_serial_default() -> *mut RduinoSerial {
+    serial(0)
+}
+
+pub unsafe fn rduino_serial_usbvirtual() -> *mut RduinoSerial {
+    ptr::null_mut()
+}
+
+pub unsafe fn rduino_serial_monitor() -> *mut RduinoSerial {
+    serial(0)
+}
+
+pub unsafe fn rduino_serial_linuxbridge() -> *mut RduinoSerial {
+    ptr::null_mut()
+}
+
+pub unsafe fn rduino_serial_hardware() -> *mut RduinoSerial {
+    serial(0)
+}
+
+pub unsafe fn rduino_serial_hardware_open() -> *mut RduinoSerial {
+    serial(1)
+}
+
+pub unsafe fn rduino_serial_ready(serial: *mut RduinoSerial) -> bool {
+    board::with_board(|board| board.serials[(*serial).port].speed.is_some())
+}
+
+pub unsafe fn rduino_serial_begin(serial: *mut RduinoSerial, speed: c_ulong,
+                                  _config: RduinoSerialConfig) -> bool {
+    board::with_board(|board| board.serials[(*serial).port].speed = Some(speed));
+    true
+}
+
+pub unsafe fn rduino_serial_end(serial: *mut RduinoSerial) {
+    board::with_board(|board| board.serials[(*serial).port].speed = None);
+}
+
+pub unsafe fn rduino_serial_set_timeout(serial: *mut RduinoSerial, timeout: c_ulong) {
+    board::with_board(|board| board.serials[(*serial).port].timeout = timeout);
+}
+
+pub unsafe fn rduino_serial_available(serial: *mut RduinoSerial) -> usize {
+    board::with_board(|board| board.serials[(*serial).port].input.len())
+}
+
+pub unsafe fn rduino_serial_read(serial: *mut RduinoSerial) -> c_int {
+    board::with_board(|board| {
+        board.serials[(*serial).port].input.pop_front().map_or(-1, |byte| byte as c_int)
+    })
+}
+
+pub unsafe fn rduino_serial_read_bytes(serial: *mut RduinoSerial, buffer: *mut u8,
+                                       length: usize) -> usize {
+    let buffer = slice::from_raw_parts_mut(buffer, length);
+    board::with_board(|board| {
+        let input = &mut board.serials[(*serial).port].input;
+        let mut count = 0;
+        while count < buffer.len() {
+            match input.pop_front() {
+                Some(byte) => buffer[count] = byte,
+                None => break
+            }
+            count += 1;
+        }
+        count
+    })
+}
+
+pub unsafe fn rduino_serial_peek(serial: *mut RduinoSerial) -> c_int {
+    board::with_board(|board| {
+        board.serials[(*serial).port].input.front().map_or(-1, |&byte| byte as c_int)
+    })
+}
+
+pub unsafe fn rduino_serial_available_for_write(_serial: *mut RduinoSerial) -> usize {
+    SERIAL_BUFFER_SIZE - 1
+}
+
+pub unsafe fn rduino_serial_write(serial: *mut RduinoSerial, value: u8) -> bool {
+    board::with_board(|board| board.serials[(*serial).port].output.push(value));
+    true
+}
+
+pub unsafe fn rduino_serial_write_bytes(serial: *mut RduinoSerial, buffer: *const u8,
+                                        length: usize) -> usize {
+    let buffer = slice::from_raw_parts(buffer, length);
+    board::with_board(|board| board.serials[(*serial).port].output.extend_from_slice(buffer));
+    length
+}
+
+pub unsafe fn rduino_serial_flush(_serial: *mut RduinoSerial) {}
+
+pub unsafe fn rduino_serial_can_write_polled(_serial: *mut RduinoSerial) -> bool {
+    true
+}
+
+pub unsafe fn rduino_serial_write_polled(serial: *mut RduinoSerial, buffer: *const u8,
+                                         length: usize) -> bool {
+    rduino_serial_write_bytes(serial, buffer, length);
+    true
+}
+
+// EEPROM
+pub unsafe fn rduino_eeprom_length() -> usize {
+    EEPROM_SIZE
+}
+
+// Like on the AVR, whose address register only has the bits needed for its EEPROM, addresses past
+// the end wrap around instead of failing.
+pub unsafe fn rduino_eeprom_read(address: u16, buffer: *mut u8, length: usize) {
+    let buffer = slice::from_raw_parts_mut(buffer, length);
+    board::with_board(|board| {
+        for (offset, byte) in buffer.iter_mut().enumerate() {
+            *byte = board.eeprom[(address as usize + offset) % EEPROM_SIZE];
+        }
+    });
+}
+
+pub unsafe fn rduino_eeprom_write(address: u16, buffer: *const u8, length: usize) {
+    let buffer = slice::from_raw_parts(buffer, length);
+    board::with_board(|board| {
+        for (offset, &byte) in buffer.iter().enumerate() {
+            board.eeprom[(address as usize + offset) % EEPROM_SIZE] = byte;
+        }
+    });
+}
+
+// Board
+pub unsafe fn rduino_reset() {
+    board::restart();
+}
@@ -0,0 +1,196 @@
+//! Implementations of the [`embedded-hal`] traits.
+//!
+//! Drivers written against `embedded-hal` can be used with the pins and serial ports of this crate
+//! directly. For the blocking delay traits a `Delay` provider is available in this module.
+//!
+//! This module is only available with the `embedded-hal` feature, which enables the optional
+//! `embedded-hal` dependency. The digital pins implement the fallible `digital::v2` traits, but
+//! reading or writing a pin never actually fails.
+//!
+//! [`embedded-hal`]: https://docs.rs/embedded-hal
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::{serial, PwmPin};
+use nb;
+
+use io::{self, Stream};
+use pins::{AnalogOutputPin, DigitalPin, Input, InterruptPin, Output, PinLevel};
+use serial::Serial;
+use time;
+
+impl<PULL> InputPin for DigitalPin<Input<PULL>> {
+    type Error = !;
+
+    #[inline]
+    fn is_high(&self) -> Result<bool, !> {
+        Ok(self.read() == PinLevel::High)
+    }
+
+    #[inline]
+    fn is_low(&self) -> Result<bool, !> {
+        Ok(self.read() == PinLevel::Low)
+    }
+}
+
+impl<PULL> InputPin for InterruptPin<PULL> {
+    type Error = !;
+
+    #[inline]
+    fn is_high(&self) -> Result<bool, !> {
+        Ok(self.read() == PinLevel::High)
+    }
+
+    #[inline]
+    fn is_low(&self) -> Result<bool, !> {
+        Ok(self.read() == PinLevel::Low)
+    }
+}
+
+impl OutputPin for DigitalPin<Output> {
+    type Error = !;
+
+    #[inline]
+    fn set_low(&mut self) -> Result<(), !> {
+        self.write(PinLevel::Low);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_high(&mut self) -> Result<(), !> {
+        self.write(PinLevel::High);
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for DigitalPin<Output> {
+    #[inline]
+    fn is_set_high(&self) -> Result<bool, !> {
+        Ok(self.read_output() == PinLevel::High)
+    }
+
+    #[inline]
+    fn is_set_low(&self) -> Result<bool, !> {
+        Ok(self.read_output() == PinLevel::Low)
+    }
+}
+
+impl PwmPin for AnalogOutputPin {
+    type Duty = u16;
+
+    #[inline]
+    fn disable(&mut self) {
+        AnalogOutputPin::disable(self);
+    }
+
+    #[inline]
+    fn enable(&mut self) {
+        AnalogOutputPin::enable(self);
+    }
+
+    #[inline]
+    fn get_duty(&self) -> u16 {
+        self.value()
+    }
+
+    #[inline]
+    fn get_max_duty(&self) -> u16 {
+        AnalogOutputPin::max_value()
+    }
+
+    #[inline]
+    fn set_duty(&mut self, duty: u16) {
+        self.write(duty);
+    }
+}
+
+
+
+impl serial::Read<u8> for Serial {
+    type Error = io::Error;
+
+    #[inline]
+    fn read(&mut self) -> nb::Result<u8, io::Error> {
+        self.read_byte().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl serial::Write<u8> for Serial {
+    type Error = io::Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), io::Error> {
+        if self.available_for_write() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        if self.write_byte(word) {
+            Ok(())
+        } else {
+            Err(nb::Error::Other(io::Error::WriteZero))
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> nb::Result<(), io::Error> {
+        Stream::flush(self);
+        Ok(())
+    }
+}
+
+
+
+/// A delay provider using the Arduino delay functions.
+///
+/// **Warning**: Millisecond delays might cause a deadlock when interrupts are disabled.
+#[derive(Debug, Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct Delay;
+
+impl Delay {
+    /// Create a new delay provider.
+    #[inline]
+    pub fn new() -> Delay {
+        Delay
+    }
+}
+
+impl DelayMs<u8> for Delay {
+    #[inline]
+    fn delay_ms(&mut self, ms: u8) {
+        time::sleep_millisecs(ms as u32);
+    }
+}
+
+impl DelayMs<u16> for Delay {
+    #[inline]
+    fn delay_ms(&mut self, ms: u16) {
+        time::sleep_millisecs(ms as u32);
+    }
+}
+
+impl DelayMs<u32> for Delay {
+    #[inline]
+    fn delay_ms(&mut self, ms: u32) {
+        time::sleep_millisecs(ms);
+    }
+}
+
+impl DelayUs<u8> for Delay {
+    #[inline]
+    fn delay_us(&mut self, us: u8) {
+        time::sleep_microsecs(us as u32);
+    }
+}
+
+impl DelayUs<u16> for Delay {
+    #[inline]
+    fn delay_us(&mut self, us: u16) {
+        time::sleep_microsecs(us as u32);
+    }
+}
+
+impl DelayUs<u32> for Delay {
+    #[inline]
+    fn delay_us(&mut self, us: u32) {
+        time::sleep_microsecs(us);
+    }
+}
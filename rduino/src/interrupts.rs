@@ -2,17 +2,28 @@
 
 pub use sys::interrupts::{__enter_isr, __exit_isr};
 
-use ffi::{self, RduinoInterruptMode};
+use ffi::{self, RduinoInterruptMode, RduinoTimer};
 use io;
+use sync::Mutex;
 use sys::interrupts;
+use sys_common::erased::{self, Erased};
 use sys_common::sync::CriticalSection;
+use sys_common::timers;
 
-use core::mem;
+use core::{fmt, mem};
+use core::marker::PhantomData;
+
+/// The maximum number of routines built by `rduino_isr_local!` that can be attached at the same
+/// time.
+pub const MAX_LOCAL_ISRS: usize = 4;
+
+/// The maximum size in bytes of the state captured by a routine built by `rduino_isr_local!`.
+pub const MAX_LOCAL_ISR_SIZE: usize = erased::MAX_SIZE;
 
 /// Trait representing a type that can be used as an external interrupt.
 ///
 /// This trait is typically implemented by `u8` which represents a raw external interrupt number and
-/// `DigitalPin` which may possibly serve as an interrupt as well.
+/// references to an `InterruptPin`, which is a digital pin that serves as an interrupt.
 pub trait ToInterrupt {
     /// Convert this type into an external interrupt number if possible.
     fn to_interrupt(self) -> Option<u8>;
@@ -53,14 +64,18 @@ impl InterruptServiceRoutine {
     /// The `mode` parameter can either be an `InterruptMode` or an `InterruptModeExt` from the
     /// platform-specific extensions if the platform supports it.
     ///
+    /// If the interrupt is given as a reference to an `InterruptPin`, the guard borrows the pin, so
+    /// the pin can not be converted back into a digital pin while the ISR is attached.
+    ///
     /// **Note**: If an ISR is already attached to the given interrupt, it is replaced.
     ///
     /// See also: [`attachInterrupt()`] from the Arduino reference.
     ///
     /// # Errors
-    /// If the given interrupt is invalid. **Note**: The Arduino SDK does not provide a reliable
-    /// way to find out if an interrupt is actually valid. So even if this method succeeds it is
-    /// unfortunately still possible that it actually failed.
+    /// If the given interrupt is invalid or does not support the given mode, e.g. pin change
+    /// interrupts only support `InterruptMode::Change`. **Note**: The Arduino SDK does not provide
+    /// a reliable way to find out if an external interrupt is actually valid. So even if this
+    /// method succeeds it is unfortunately still possible that it actually failed.
     ///
     /// # Panics
     /// Panics if this function is called from inside an interrupt service routine. Because the
@@ -68,29 +83,243 @@ impl InterruptServiceRoutine {
     /// an unsafe API.
     ///
     /// [`attachInterrupt()`]: https://www.arduino.cc/en/Reference/AttachInterrupt
-    pub fn attach<I: ToInterrupt, M: Into<InterruptMode>>(self, interrupt: I, mode: M) -> io::Result<IsrGuard> {
+    pub fn attach<'a, I, M>(self, interrupt: I, mode: M) -> io::Result<IsrGuard<'a>>
+            where I: ToInterrupt + 'a, M: Into<InterruptMode> {
         check_isr!("InterruptServiceRoutine::attach");
 
         let interrupt = interrupt.to_interrupt().map_or_else(|| Err(io::Error::InvalidInterruptPin), Ok)?;
+        let mode = mode.into().to_ffi();
+        if unsafe { ffi::rduino_attach_interrupt(interrupt, Some(self.routine), mode) } {
+            Ok(IsrGuard { interrupt: interrupt, local: None, _interrupt: PhantomData })
+        } else {
+            Err(io::Error::UnsupportedInterruptMode)
+        }
+    }
+
+    /// Attach this ISR to a hardware timer.
+    ///
+    /// Configures the timer to generate an interrupt `frequency_hz` times per second and runs the
+    /// interrupt service routine every time. In case of success, returns a guard that stops the
+    /// timer when it goes out of scope. Because the routine is run by the hardware, its timing is
+    /// not affected by whatever the main program is doing.
+    ///
+    /// The available timers are listed by the `TimerExt` enum of the platform-specific extensions.
+    /// The actual frequency is the nearest one the timer can produce by dividing the system clock,
+    /// so it may be slightly off for frequencies that do not divide it evenly.
+    ///
+    /// # Errors
+    /// Returns `TimerBusy` if an ISR is already attached to the timer, or if the timer generates
+    /// tones and a tone is being generated, such as `Timer2` on AVR. Returns
+    /// `UnsupportedFrequency` if the timer can not produce the given frequency.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine.
+    pub fn attach_timer<T: Into<Timer>>(self, timer: T, frequency_hz: u32)
+                                        -> io::Result<TimerGuard> {
+        check_isr!("InterruptServiceRoutine::attach_timer");
+
+        let timer = timer.into().to_ffi();
+        timers::claim_timer(timer)?;
+        if unsafe { ffi::rduino_timer_attach(timer, frequency_hz, Some(self.routine)) } {
+            Ok(TimerGuard { timer: timer, local: None })
+        } else {
+            timers::release_timer(timer);
+            Err(io::Error::UnsupportedFrequency)
+        }
+    }
+}
+
+
+
+/// An interrupt service routine (or ISR) that owns its state.
+///
+/// Unlike the closure of a routine built by `rduino_isr!`, the closure of this routine can capture
+/// variables by value, e.g. a counter or the sending half of a channel. When the routine is
+/// attached, the closure is moved into one of `MAX_LOCAL_ISRS` static slots, so no allocation is
+/// needed. The slot is cleared and the closure is dropped when the routine is detached.
+///
+/// A local ISR can be constructed with the `rduino_isr_local!` macro.
+pub struct LocalInterruptServiceRoutine<F> {
+    closure: F
+}
+
+impl<F: FnMut() + Send + 'static> LocalInterruptServiceRoutine<F> {
+    #[doc(hidden)]
+    #[inline]
+    pub fn __new(closure: F) -> LocalInterruptServiceRoutine<F> {
+        LocalInterruptServiceRoutine {
+            closure: closure
+        }
+    }
+
+    /// Attach this ISR to an interrupt.
+    ///
+    /// Behaves like `InterruptServiceRoutine::attach`.
+    ///
+    /// # Errors
+    /// If the given interrupt is invalid or does not support the given mode, or if all slots for
+    /// local ISRs are in use.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine or if the
+    /// closure is larger than `MAX_LOCAL_ISR_SIZE`.
+    pub fn attach<'a, I, M>(self, interrupt: I, mode: M) -> io::Result<IsrGuard<'a>>
+            where I: ToInterrupt + 'a, M: Into<InterruptMode> {
+        check_isr!("LocalInterruptServiceRoutine::attach");
+
+        let interrupt = interrupt.to_interrupt().ok_or(io::Error::InvalidInterruptPin)?;
+        let slot = store_local_isr(self.closure)?;
+        let routine = InterruptServiceRoutine::__new(LOCAL_ISR_ROUTINES[slot]);
+        match routine.attach(interrupt, mode) {
+            Ok(mut guard) => {
+                guard.local = Some(slot);
+                Ok(guard)
+            }
+            Err(error) => {
+                clear_local_isr(slot);
+                Err(error)
+            }
+        }
+    }
+
+    /// Attach this ISR to a hardware timer.
+    ///
+    /// Behaves like `InterruptServiceRoutine::attach_timer`.
+    ///
+    /// # Errors
+    /// If the timer is busy or can not produce the given frequency, or if all slots for local ISRs
+    /// are in use.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine or if the
+    /// closure is larger than `MAX_LOCAL_ISR_SIZE`.
+    pub fn attach_timer<T: Into<Timer>>(self, timer: T, frequency_hz: u32)
+                                        -> io::Result<TimerGuard> {
+        check_isr!("LocalInterruptServiceRoutine::attach_timer");
+
+        let slot = store_local_isr(self.closure)?;
+        let routine = InterruptServiceRoutine::__new(LOCAL_ISR_ROUTINES[slot]);
+        match routine.attach_timer(timer, frequency_hz) {
+            Ok(mut guard) => {
+                guard.local = Some(slot);
+                Ok(guard)
+            }
+            Err(error) => {
+                clear_local_isr(slot);
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for LocalInterruptServiceRoutine<F> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("LocalInterruptServiceRoutine").finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct LocalIsrSlot {
+    closure: Erased,
+    call: Option<unsafe fn(*mut u8)>
+}
+
+const EMPTY_LOCAL_ISR: LocalIsrSlot = LocalIsrSlot {
+    closure: erased::EMPTY,
+    call: None
+};
+
+static LOCAL_ISRS: Mutex<[LocalIsrSlot; MAX_LOCAL_ISRS]> =
+    Mutex::new([EMPTY_LOCAL_ISR; MAX_LOCAL_ISRS]);
+
+unsafe fn call_closure<F: FnMut()>(closure: *mut u8) {
+    (*(closure as *mut F))()
+}
+
+fn store_local_isr<F: FnMut() + Send + 'static>(closure: F) -> io::Result<usize> {
+    let closure = Erased::new(closure)
+                         .expect("state of the interrupt service routine is too large");
+
+    let index = {
+        let mut slots = LOCAL_ISRS.lock();
+        let index = slots.iter().position(|slot| slot.call.is_none());
+        if let Some(index) = index {
+            slots[index] = LocalIsrSlot {
+                closure: closure,
+                call: Some(call_closure::<F>)
+            };
+        }
+        index
+    };
+
+    match index {
+        Some(index) => Ok(index),
+        None => {
+            closure.dispose();
+            Err(io::Error::NoFreeSlot)
+        }
+    }
+}
+
+fn clear_local_isr(index: usize) {
+    // The closure is moved out of its slot, so it is dropped outside of the critical section.
+    let slot = mem::replace(&mut LOCAL_ISRS.lock()[index], EMPTY_LOCAL_ISR);
+    slot.closure.dispose();
+}
+
+fn run_local_isr(index: usize) {
+    // The closure is called outside of the critical section, so it does not keep other interrupts
+    // disabled. Its slot can not be cleared while it runs, because only the guard clears it and
+    // the guard belongs to the main program, which the routine has interrupted.
+    let (call, closure) = {
+        let mut slots = LOCAL_ISRS.lock();
+        let slot = &mut slots[index];
+        (slot.call, slot.closure.as_mut_ptr())
+    };
+    if let Some(call) = call {
         unsafe {
-            ffi::rduino_attach_interrupt(interrupt, Some(self.routine), mode.into().to_ffi());
+            call(closure);
         }
-        Ok(IsrGuard { interrupt: interrupt })
     }
 }
 
+macro_rules! local_isr_routines {
+    ($($routine:ident => $slot:expr),*) => {
+        $(
+            extern "C" fn $routine() {
+                __enter_isr();
+                run_local_isr($slot);
+                __exit_isr();
+            }
+        )*
+
+        static LOCAL_ISR_ROUTINES: [extern "C" fn(); MAX_LOCAL_ISRS] = [$($routine),*];
+    }
+}
+
+local_isr_routines! {
+    local_isr_0 => 0,
+    local_isr_1 => 1,
+    local_isr_2 => 2,
+    local_isr_3 => 3
+}
+
 
 
 /// A RAII implementation of a scoped interrupt service routine.
 ///
-/// When this structure is dropped or `detach` is called, the ISR is detached.
+/// When this structure is dropped or `detach` is called, the ISR is detached. If the routine was
+/// built by `rduino_isr_local!`, its state is dropped as well. If the ISR was attached to an
+/// `InterruptPin`, the guard borrows that pin for its lifetime `'a`.
 #[must_use]
 #[derive(Debug)]
-pub struct IsrGuard {
-    interrupt: u8
+pub struct IsrGuard<'a> {
+    interrupt: u8,
+    local: Option<usize>,
+    _interrupt: PhantomData<&'a ()>
 }
 
-impl IsrGuard {
+impl<'a> IsrGuard<'a> {
     /// Detach the interrupt service routine.
     ///
     /// This has the same effect as dropping the guard.
@@ -99,15 +328,75 @@ impl IsrGuard {
     }
 }
 
-impl Drop for IsrGuard {
+impl<'a> Drop for IsrGuard<'a> {
     fn drop(&mut self) {
         unsafe {
             ffi::rduino_detach_interrupt(self.interrupt);
         }
+        if let Some(slot) = self.local {
+            clear_local_isr(slot);
+        }
+    }
+}
+
+impl<'a> !Send for IsrGuard<'a> {}
+
+
+
+/// A RAII implementation of a periodic timer interrupt.
+///
+/// When this structure is dropped or `detach` is called, the timer is stopped and the ISR is
+/// detached. If the routine was built by `rduino_isr_local!`, its state is dropped as well.
+#[must_use]
+#[derive(Debug)]
+pub struct TimerGuard {
+    timer: RduinoTimer,
+    local: Option<usize>
+}
+
+impl TimerGuard {
+    /// Stop the timer and detach the interrupt service routine.
+    ///
+    /// This has the same effect as dropping the guard.
+    pub fn detach(self) {
+        mem::drop(self)
+    }
+}
+
+impl Drop for TimerGuard {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::rduino_timer_detach(self.timer);
+        }
+        timers::release_timer(self.timer);
+        if let Some(slot) = self.local {
+            clear_local_isr(slot);
+        }
     }
 }
 
-impl !Send for IsrGuard {}
+impl !Send for TimerGuard {}
+
+
+
+/// A hardware timer that can generate periodic interrupts.
+///
+/// Timers are platform-specific, so values of this type are created from the `TimerExt` enum of
+/// the platform-specific extensions.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum Timer {
+    #[doc(hidden)]
+    __Raw(RduinoTimer)
+}
+
+impl Timer {
+    #[inline]
+    fn to_ffi(self) -> RduinoTimer {
+        match self {
+            Timer::__Raw(timer) => timer
+        }
+    }
+}
 
 
 
@@ -158,6 +447,9 @@ pub fn without_interrupts<T, F: FnOnce() -> T>(f: F) -> T {
 }
 
 /// Whether the code is called from inside an interrupt service routine.
+///
+/// When interrupt service routines are nested, this returns `true` until the outermost routine
+/// returns.
 #[inline]
 pub fn inside_isr() -> bool {
     interrupts::inside_isr()
@@ -1,11 +1,14 @@
 //! Traits, helpers and type definitions for core I/O functionality.
 
-use serial::{Serial, SerialPort};
+use serial::__Monitor;
+use sys::statics::BoardLocal;
+use sys_common::erased::{self, Erased};
 
 #[cfg(feature = "alloc")]
 use collections::Vec;
 
 use core::fmt;
+use core::mem;
 use core::result;
 
 pub mod prelude {
@@ -14,12 +17,27 @@ pub mod prelude {
 
 pub type Result<T> = result::Result<T, Error>;
 
+/// The maximum size in bytes of a target passed to `set_print` or `set_eprint`.
+pub const MAX_PRINT_TARGET_SIZE: usize = erased::MAX_SIZE;
+
 #[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
 pub enum Error {
     WriteZero,
     UnexpectedEof,
     InvalidInterruptPin,
+    UnsupportedInterruptMode,
     UnsupportedSerialMode,
+    PortUnavailable,
+    PortBusy,
+    ToneBusy,
+    TimerBusy,
+    UnsupportedFrequency,
+    AddressNack,
+    DataNack,
+    BufferFull,
+    NoFreeSlot,
+    InvalidAddress,
+    TimedOut,
     Other,
 
     #[doc(hidden)]
@@ -32,7 +50,19 @@ impl fmt::Display for Error {
             Error::WriteZero => write!(fmt, "write zero"),
             Error::UnexpectedEof => write!(fmt, "unexpected end of file"),
             Error::InvalidInterruptPin => write!(fmt, "invalid interrupt pin"),
+            Error::UnsupportedInterruptMode => write!(fmt, "unsupported interrupt mode"),
             Error::UnsupportedSerialMode => write!(fmt, "unsupported serial mode"),
+            Error::PortUnavailable => write!(fmt, "serial port unavailable"),
+            Error::PortBusy => write!(fmt, "serial port busy"),
+            Error::ToneBusy => write!(fmt, "tone generator busy"),
+            Error::TimerBusy => write!(fmt, "timer busy"),
+            Error::UnsupportedFrequency => write!(fmt, "unsupported frequency"),
+            Error::AddressNack => write!(fmt, "address not acknowledged"),
+            Error::DataNack => write!(fmt, "data not acknowledged"),
+            Error::BufferFull => write!(fmt, "buffer full"),
+            Error::NoFreeSlot => write!(fmt, "no free slot"),
+            Error::InvalidAddress => write!(fmt, "invalid address"),
+            Error::TimedOut => write!(fmt, "timed out"),
             Error::Other => write!(fmt, "other error"),
             Error::__NonExhaustive => unreachable!()
         }
@@ -251,10 +281,193 @@ fn read_to_end<R: Read + ?Sized>(r: &mut R, buf: &mut Vec<u8>) -> Result<usize>
     result
 }
 
+
+
+/// Redirect `print!` and `println!` to the given target instead of the serial monitor.
+///
+/// The target is moved into a static slot of `MAX_PRINT_TARGET_SIZE` bytes, so no allocation is
+/// needed. Printing writes to it with interrupts enabled; anything printed while the target is
+/// already in use, e.g. by an interrupt service routine that interrupts a print, is discarded.
+///
+/// The macros print to the serial monitor again when the returned guard is dropped, which drops
+/// the target as well.
+///
+/// # Errors
+/// Returns `NoFreeSlot` if a target is already set.
+///
+/// # Panics
+/// Panics if the target is larger than `MAX_PRINT_TARGET_SIZE`.
+pub fn set_print<W: Write + Send + 'static>(target: W) -> Result<PrintGuard> {
+    set_target(PRINT, target)
+}
+
+/// Redirect `eprint!` and `eprintln!` to the given target instead of the serial monitor.
+///
+/// This works just like `set_print`, so the target shares its restrictions.
+///
+/// # Errors
+/// Returns `NoFreeSlot` if a target is already set.
+///
+/// # Panics
+/// Panics if the target is larger than `MAX_PRINT_TARGET_SIZE`.
+pub fn set_eprint<W: Write + Send + 'static>(target: W) -> Result<PrintGuard> {
+    set_target(EPRINT, target)
+}
+
+fn set_target<W: Write + Send + 'static>(output: usize, target: W) -> Result<PrintGuard> {
+    let target = PrintTarget::new(target);
+    let rejected = with_print_slot(output, move |slot| {
+        if let PrintSlot::Monitor = *slot {
+            *slot = PrintSlot::Target(target);
+            None
+        } else {
+            Some(target)
+        }
+    });
+
+    match rejected {
+        Some(target) => {
+            target.dispose();
+            Err(Error::NoFreeSlot)
+        }
+        None => Ok(PrintGuard { output: output })
+    }
+}
+
+/// A RAII implementation of a scoped print target.
+///
+/// When this structure is dropped or `restore` is called, the macros that were redirected write to
+/// the serial monitor again and the target is dropped.
+#[must_use]
+#[derive(Debug)]
+pub struct PrintGuard {
+    output: usize
+}
+
+impl PrintGuard {
+    /// Print to the serial monitor again.
+    ///
+    /// This has the same effect as dropping the guard.
+    pub fn restore(self) {
+        mem::drop(self)
+    }
+}
+
+impl Drop for PrintGuard {
+    fn drop(&mut self) {
+        let old = with_print_slot(self.output, |slot| {
+            if let PrintSlot::InUse { ref mut restore } = *slot {
+                // The print that is using the target drops it when it is done.
+                *restore = true;
+                return None;
+            }
+            Some(mem::replace(slot, PrintSlot::Monitor))
+        });
+
+        // The target is dropped outside of the critical section.
+        if let Some(PrintSlot::Target(target)) = old {
+            target.dispose();
+        }
+    }
+}
+
+impl !Send for PrintGuard {}
+
+// Like `Erased`, cloning a target does not clone the value, which is fine for the empty initial
+// slots that are the only ones ever cloned.
+#[derive(Clone)]
+struct PrintTarget {
+    target: Erased,
+    write_fmt: unsafe fn(*mut u8, fmt::Arguments) -> Result<()>
+}
+
+impl PrintTarget {
+    fn new<W: Write + Send + 'static>(target: W) -> PrintTarget {
+        PrintTarget {
+            target: Erased::new(target).expect("print target is too large"),
+            write_fmt: write_fmt_target::<W>
+        }
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        unsafe { (self.write_fmt)(self.target.as_mut_ptr(), args) }
+    }
+
+    fn dispose(self) {
+        self.target.dispose();
+    }
+}
+
+unsafe fn write_fmt_target<W: Write>(target: *mut u8, args: fmt::Arguments) -> Result<()> {
+    (*(target as *mut W)).write_fmt(args)
+}
+
+#[derive(Clone)]
+enum PrintSlot {
+    Monitor,
+    Target(PrintTarget),
+    // The target is taken out of the slot while printing, so it is written to without holding the
+    // lock. If `restore` is set, the print drops the target instead of putting it back.
+    InUse { restore: bool }
+}
+
+// The outputs that can be redirected, as indices into the print slots.
+const PRINT: usize = 0;
+const EPRINT: usize = 1;
+
+fn with_print_slot<R, F: FnOnce(&mut PrintSlot) -> R>(output: usize, f: F) -> R {
+    static PRINT_SLOTS: [BoardLocal<PrintSlot>; 2] =
+        [BoardLocal::new(PrintSlot::Monitor), BoardLocal::new(PrintSlot::Monitor)];
+
+    PRINT_SLOTS[output].with(f)
+}
+
+fn write_output(output: usize, args: fmt::Arguments) -> Result<()> {
+    let taken = with_print_slot(output, |slot| {
+        match *slot {
+            PrintSlot::Monitor => return PrintSlot::Monitor,
+            PrintSlot::InUse { .. } => return PrintSlot::InUse { restore: false },
+            PrintSlot::Target(_) => {}
+        }
+        mem::replace(slot, PrintSlot::InUse { restore: false })
+    });
+
+    match taken {
+        PrintSlot::Monitor => __Monitor::new().write_fmt(args),
+        PrintSlot::Target(mut target) => {
+            let result = target.write_fmt(args);
+            let old = with_print_slot(output, move |slot| {
+                if let PrintSlot::InUse { restore: true } = *slot {
+                    *slot = PrintSlot::Monitor;
+                    Some(target)
+                } else {
+                    *slot = PrintSlot::Target(target);
+                    None
+                }
+            });
+            if let Some(target) = old {
+                target.dispose();
+            }
+            result
+        }
+        PrintSlot::InUse { .. } => Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn __try_print(args: fmt::Arguments) -> Result<()> {
+    write_output(PRINT, args)
+}
+
 #[doc(hidden)]
 pub fn __print(args: fmt::Arguments) {
-    let mut serial = Serial::open(SerialPort::Monitor).unwrap_or_default();
-    if let Err(error) = serial.write_fmt(args) {
-        panic!("failed printing to serial monitor: {}", error);
+    if let Err(error) = __try_print(args) {
+        panic!("failed printing: {}", error);
     }
 }
+
+#[doc(hidden)]
+pub fn __eprint(args: fmt::Arguments) {
+    // Error output is often written when things already went wrong, so failures are ignored.
+    let _ = write_output(EPRINT, args);
+}
@@ -1,43 +1,71 @@
 #![no_std]
 
 #![feature(asm)]
+#![feature(cfg_target_has_atomic)]
 #![feature(compiler_builtins_lib)]
 #![feature(const_fn)]
 #![feature(core_float)]
 #![feature(core_intrinsics)]
+#![feature(integer_atomics)]
 #![feature(lang_items)]
 #![feature(optin_builtin_traits)]
 #![cfg_attr(feature = "alloc", feature(alloc, collections))]
+#![cfg_attr(feature = "embedded-hal", feature(never_type))]
 
 //#![warn(missing_docs)]
 
+#[cfg(not(feature = "host-sim"))]
 extern crate compiler_builtins;
 extern crate libc_arduino as libc;
 
+#[cfg(feature = "host-sim")]
+#[macro_use(thread_local)]
+extern crate std;
+
 #[cfg(feature = "alloc")]
 extern crate alloc_arduino;
 
+#[cfg(feature = "embedded-hal")]
+extern crate embedded_hal;
+
+#[cfg(feature = "embedded-hal")]
+extern crate nb;
+
+#[cfg(feature = "log")]
+extern crate log;
+
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
 #[cfg(feature = "alloc")]
 extern crate collections;
 
-pub use sys_common::{__rduino_setup, __rduino_loop};
+#[cfg(not(feature = "host-sim"))]
+pub use sys_common::{__rduino_setup, __rduino_loop, __rduino_yield};
 
 #[macro_use]
 mod macros;
 
+pub mod executor;
 pub mod ffi;
+#[cfg(feature = "embedded-hal")]
+pub mod hal;
 pub mod interrupts;
 pub mod io;
+#[cfg(feature = "log")]
+pub mod logger;
 pub mod num;
 pub mod panic;
 pub mod platform;
 pub mod pins;
+pub mod pulse;
+pub mod random;
 pub mod serial;
+#[cfg(feature = "host-sim")]
+pub mod sim;
 pub mod sync;
 pub mod time;
+pub mod timer;
 
 mod sys;
 mod sys_common;
@@ -0,0 +1,71 @@
+//! A backend for the `log` crate.
+//!
+//! Once installed with `init`, messages logged through the `log` macros, both by the firmware and
+//! by any libraries it uses, are printed like `print!` does. Every line is prefixed with the value
+//! of `time::millisecs` and the level and target of the message:
+//!
+//! ```text
+//! [1500 INFO  my_firmware] sensor ready
+//! ```
+//!
+//! This module is only available with the `log` feature.
+
+use io;
+#[cfg(target_arch = "avr")]
+use sys_common::sync::CriticalSection;
+use time;
+
+use log::{self, Log, LevelFilter, Metadata, Record, SetLoggerError};
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // There is nowhere to report a failure to, so the message is dropped instead.
+        let _ = io::__try_print(format_args!("[{} {:<5} {}] {}\n",
+                                             time::millisecs(),
+                                             record.level(),
+                                             record.target(),
+                                             record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger;
+
+/// Install the logger, only letting through messages up to the given level.
+///
+/// # Errors
+/// Returns an error if another logger was installed before.
+pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
+    set_logger()?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Change the most verbose level that is let through.
+#[inline]
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+#[cfg(not(target_arch = "avr"))]
+fn set_logger() -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)
+}
+
+#[cfg(target_arch = "avr")]
+fn set_logger() -> Result<(), SetLoggerError> {
+    // AVR has no compare-and-swap, so the logger is installed with interrupts disabled instead.
+    let _lock = CriticalSection::enter();
+    unsafe { log::set_logger_racy(&LOGGER) }
+}
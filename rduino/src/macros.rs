@@ -18,24 +18,6 @@ macro_rules! rduino_main {
     }
 }
 
-// #[macro_export]
-// #[cfg(feature = "alloc")]
-// macro_rules! rduino_isr_local {
-//     ($f:expr) => {{
-//         static __CLOSURE: $crate::sync::Mutex<Option<Box<Fn() + Send + Sync>>> = $crate::sync::Mutex::new(None);
-
-//         extern fn __routine() {
-//             let closure = __CLOSURE.lock();
-//             let closure = closure.as_ref().unwrap();
-//             closure();
-//         }
-
-//         *__CLOSURE.lock() = Some(Box::new($f));
-
-//         $crate::interrupts::InterruptServiceRoutine::__new(__routine)
-//     }}
-// }
-
 #[macro_export]
 macro_rules! rduino_isr {
     ($f:expr) => {{
@@ -54,6 +36,13 @@ macro_rules! rduino_isr {
     }}
 }
 
+#[macro_export]
+macro_rules! rduino_isr_local {
+    ($f:expr) => {
+        $crate::interrupts::LocalInterruptServiceRoutine::__new($f)
+    }
+}
+
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => ($crate::io::__print(format_args!($($arg)*)));
@@ -66,6 +55,45 @@ macro_rules! println {
     ($fmt:expr, $($arg:tt)*) => (print!(concat!($fmt, "\n"), $($arg)*));
 }
 
+#[macro_export]
+macro_rules! try_print {
+    ($($arg:tt)*) => ($crate::io::__try_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! try_println {
+    () => (try_print!("\n"));
+    ($fmt:expr) => (try_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => (try_print!(concat!($fmt, "\n"), $($arg)*));
+}
+
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ($crate::io::__eprint(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    () => (eprint!("\n"));
+    ($fmt:expr) => (eprint!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => (eprint!(concat!($fmt, "\n"), $($arg)*));
+}
+
+#[macro_export]
+macro_rules! dbg {
+    () => ($crate::io::__print(format_args!("[{}:{}]\n", file!(), line!())));
+    ($val:expr) => {
+        // The value is moved into a binding, so it is only evaluated once and can be returned.
+        match $val {
+            tmp => {
+                $crate::io::__print(format_args!("[{}:{}] {} = {:#?}\n",
+                                                 file!(), line!(), stringify!($val), &tmp));
+                tmp
+            }
+        }
+    }
+}
+
 macro_rules! check_isr {
     ($fun:expr) => {
         if $crate::interrupts::inside_isr() {
@@ -1,10 +1,12 @@
 //! Panic handling.
 
+use ffi::{self, RduinoPinLevel, RduinoPinMode, RduinoSerial};
+use io::{self, Write};
 use sync::Mutex;
-use sys::interrupts;
+use sys::statics::BoardLocal;
 
+use core::{fmt, mem, slice, str};
 use core::fmt::Arguments;
-use core::intrinsics;
 
 static PANIC_HANDLER: Mutex<Option<fn(&PanicInfo)>> = Mutex::new(None);
 
@@ -18,6 +20,17 @@ pub struct PanicInfo<'a> {
 }
 
 impl<'a> PanicInfo<'a> {
+    #[doc(hidden)]
+    pub fn __new(payload: Arguments<'a>, file: &'a str, line: u32) -> PanicInfo<'a> {
+        PanicInfo {
+            payload: payload,
+            location: Location {
+                file: file,
+                line: line
+            }
+        }
+    }
+
     /// The payload associated with the panic.
     pub fn payload(&self) -> &Arguments {
         &self.payload
@@ -57,6 +70,8 @@ impl<'a> Location<'a> {
 /// and the runtime simply aborts. Panics inside of an interrupt serivce routine *always* cause
 /// the runtime to abort.
 ///
+/// `DefaultHook` provides a hook that reports the panic over the serial monitor.
+///
 /// The hook is provided with a `PanicInfo` struct which contains information about the origin of
 /// the panic, including the payload passed to `panic!` and the source code location from which the
 /// panic originated.
@@ -78,28 +93,380 @@ pub fn take_hook() -> Option<fn(&PanicInfo)> {
     handler.take()
 }
 
-#[lang = "panic_fmt"]
-extern fn rust_begin_panic(message: ::core::fmt::Arguments, file: &'static str, line: u32) -> ! {
-    unsafe {
-        interrupts::disable_interrupts();
+/// A panic hook that reports the panic over the serial monitor and with a status LED.
+///
+/// Since interrupts are disabled during a panic, the report is written by polling the serial port
+/// directly. This is only supported for hardware serial ports on AVR, so `install` fails on other
+/// boards unless reporting is turned off. The port must have been started with `Serial::reset`
+/// before the panic.
+///
+/// After the report, the hook blinks the LED in a pattern of three short flashes and, if
+/// configured, resets the board. If neither is configured, the hook returns immediately and the
+/// runtime aborts. On AVR the board is reset by the watchdog timer, so the bootloader has to
+/// disable it again after the reset, as recent versions of Optiboot do.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct DefaultHook {
+    report: bool,
+    led: Option<u8>,
+    reset_after_ms: Option<u32>
+}
+
+const DEFAULT_HOOK: DefaultHook = DefaultHook {
+    report: true,
+    led: None,
+    reset_after_ms: None
+};
+
+static DEFAULT_HOOK_CONFIG: Mutex<DefaultHook> = Mutex::new(DEFAULT_HOOK);
+
+// The LED pattern as pairs of a level and a duration in milliseconds.
+const BLINK_PATTERN: [(RduinoPinLevel, u16); 6] = [
+    (RduinoPinLevel::High, 150),
+    (RduinoPinLevel::Low, 150),
+    (RduinoPinLevel::High, 150),
+    (RduinoPinLevel::Low, 150),
+    (RduinoPinLevel::High, 150),
+    (RduinoPinLevel::Low, 1000)
+];
+
+impl DefaultHook {
+    /// A hook that only reports the panic over the serial monitor.
+    pub fn new() -> DefaultHook {
+        DEFAULT_HOOK
     }
 
-    if !interrupts::inside_isr() {
-        let handler = take_hook();
-        if let Some(handler) = handler {
-            let location = Location {
-                file: file,
-                line: line
-            };
-            let info = PanicInfo {
-                payload: message,
-                location: location
-            };
-            handler(&info);
+    /// Set whether the payload and location of the panic are written to the serial monitor.
+    #[inline]
+    pub fn report(mut self, report: bool) -> DefaultHook {
+        self.report = report;
+        self
+    }
+
+    /// Blink the LED on the given digital pin after a panic.
+    ///
+    /// The pin is used directly, even if it was taken from `Pins` by someone else.
+    #[inline]
+    pub fn led(mut self, pin: u8) -> DefaultHook {
+        self.led = Some(pin);
+        self
+    }
+
+    /// Reset the board the given number of milliseconds after the panic was reported.
+    #[inline]
+    pub fn reset_after(mut self, delay_ms: u32) -> DefaultHook {
+        self.reset_after_ms = Some(delay_ms);
+        self
+    }
+
+    /// Register this hook, replacing any that was previously registered.
+    ///
+    /// # Errors
+    /// Returns `PortUnavailable` if the panic should be reported, but the serial monitor can not be
+    /// written to while interrupts are disabled. This is the case for USB ports and for every port
+    /// on SAMD boards.
+    pub fn install(self) -> io::Result<()> {
+        if self.report && !PolledMonitor::new().is_supported() {
+            return Err(io::Error::PortUnavailable);
         }
+        *DEFAULT_HOOK_CONFIG.lock() = self;
+        set_hook(default_hook);
+        Ok(())
+    }
+}
+
+impl Default for DefaultHook {
+    #[inline]
+    fn default() -> DefaultHook {
+        DefaultHook::new()
+    }
+}
+
+fn default_hook(info: &PanicInfo) {
+    let config = *DEFAULT_HOOK_CONFIG.lock();
+    if config.report {
+        let _ = writeln!(PolledMonitor::new(), "panicked at '{}', {}:{}",
+                         info.payload, info.location.file, info.location.line);
+    }
+
+    if config.led.is_none() && config.reset_after_ms.is_none() {
+        return;
+    }
+    if let Some(pin) = config.led {
+        unsafe {
+            ffi::rduino_pin_mode(pin, RduinoPinMode::Output);
+        }
+    }
+
+    // The clock stops while interrupts are disabled, so time is kept by busy waiting.
+    let mut elapsed_ms = 0u32;
+    loop {
+        for &(level, duration_ms) in BLINK_PATTERN.iter() {
+            if let Some(pin) = config.led {
+                unsafe {
+                    ffi::rduino_digital_write(pin, level);
+                }
+            }
+            for _ in 0..duration_ms {
+                if config.reset_after_ms.map_or(false, |delay_ms| elapsed_ms >= delay_ms) {
+                    unsafe {
+                        ffi::rduino_reset();
+                    }
+                    return;
+                }
+                unsafe {
+                    ffi::rduino_delay_microseconds(1000);
+                }
+                elapsed_ms = elapsed_ms.saturating_add(1);
+            }
+        }
+    }
+}
+
+/// The serial monitor, written to without relying on interrupts.
+struct PolledMonitor {
+    inner: *mut RduinoSerial
+}
+
+impl PolledMonitor {
+    fn new() -> PolledMonitor {
+        let mut inner = unsafe { ffi::rduino_serial_monitor() };
+        if inner.is_null() {
+            inner = unsafe { ffi::rduino_serial_default() };
+        }
+        PolledMonitor { inner: inner }
+    }
+
+    fn is_supported(&self) -> bool {
+        unsafe { ffi::rduino_serial_can_write_polled(self.inner) }
+    }
+}
+
+impl Write for PolledMonitor {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if unsafe { ffi::rduino_serial_write_polled(self.inner, buf.as_ptr(), buf.len()) } {
+            Ok(buf.len())
+        } else {
+            Err(io::Error::Other)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The maximum length in bytes of the message kept by `last_panic`.
+pub const MAX_PANIC_MESSAGE_LEN: usize = 64;
+
+/// The maximum length in bytes of the file name kept by `last_panic`.
+pub const MAX_PANIC_FILE_LEN: usize = 32;
+
+/// The number of bytes of EEPROM used by `set_eeprom_backup`.
+pub const PANIC_RECORD_SIZE: usize = 108;
+
+const RECORD_MAGIC: u16 = 0x5052;
+
+// The layout has no padding, so the record can be copied to and from EEPROM byte by byte.
+#[repr(C)]
+#[derive(Copy)]
+struct PanicRecord {
+    magic: u16,
+    checksum: u16,
+    line: u32,
+    file_len: u16,
+    message_len: u16,
+    file: [u8; MAX_PANIC_FILE_LEN],
+    message: [u8; MAX_PANIC_MESSAGE_LEN]
+}
+
+const EMPTY_RECORD: PanicRecord = PanicRecord {
+    magic: 0,
+    checksum: 0,
+    line: 0,
+    file_len: 0,
+    message_len: 0,
+    file: [0; MAX_PANIC_FILE_LEN],
+    message: [0; MAX_PANIC_MESSAGE_LEN]
+};
+
+impl PanicRecord {
+    fn checksum(&self) -> u16 {
+        let header = [self.line as u8, (self.line >> 8) as u8, (self.line >> 16) as u8,
+                      (self.line >> 24) as u8, self.file_len as u8, self.message_len as u8];
+        header.iter().chain(self.file.iter()).chain(self.message.iter()).fold(0, |sum, &byte| {
+            sum.rotate_left(1) ^ byte as u16
+        })
     }
 
-    unsafe {
-        intrinsics::abort();
+    fn is_valid(&self) -> bool {
+        self.magic == RECORD_MAGIC
+            && self.file_len as usize <= MAX_PANIC_FILE_LEN
+            && self.message_len as usize <= MAX_PANIC_MESSAGE_LEN
+            && self.checksum == self.checksum()
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        debug_assert_eq!(mem::size_of::<PanicRecord>(), PANIC_RECORD_SIZE);
+        unsafe { slice::from_raw_parts(self as *const PanicRecord as *const u8, PANIC_RECORD_SIZE) }
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        debug_assert_eq!(mem::size_of::<PanicRecord>(), PANIC_RECORD_SIZE);
+        unsafe { slice::from_raw_parts_mut(self as *mut PanicRecord as *mut u8, PANIC_RECORD_SIZE) }
+    }
+}
+
+impl Clone for PanicRecord {
+    fn clone(&self) -> PanicRecord {
+        *self
+    }
+}
+
+// Formats into a fixed buffer, dropping whatever does not fit without splitting characters.
+struct Truncate<'a> {
+    buf: &'a mut [u8],
+    len: usize
+}
+
+impl<'a> fmt::Write for Truncate<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(self.buf.len() - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+// The section is not initialized on startup, so the record survives a reset. The SAMD linker
+// scripts have no such section, so there the record is cleared like any other static.
+#[cfg_attr(target_arch = "avr", link_section = ".noinit")]
+static LAST_PANIC: BoardLocal<PanicRecord> = BoardLocal::noinit(EMPTY_RECORD);
+
+static EEPROM_BACKUP: BoardLocal<Option<u16>> = BoardLocal::new(None);
+
+/// Information about a panic that happened before the board was last reset.
+///
+/// This structure is returned by `last_panic`.
+#[derive(Clone, Copy)]
+pub struct LastPanic {
+    record: PanicRecord
+}
+
+impl LastPanic {
+    /// The payload of the panic, truncated to `MAX_PANIC_MESSAGE_LEN` bytes.
+    pub fn message(&self) -> &str {
+        str::from_utf8(&self.record.message[..self.record.message_len as usize]).unwrap_or("")
+    }
+
+    /// The source file from which the panic originated, truncated to `MAX_PANIC_FILE_LEN` bytes.
+    pub fn file(&self) -> &str {
+        str::from_utf8(&self.record.file[..self.record.file_len as usize]).unwrap_or("")
+    }
+
+    /// The line number from which the panic originated.
+    pub fn line(&self) -> u32 {
+        self.record.line
+    }
+}
+
+impl fmt::Debug for LastPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LastPanic")
+         .field("message", &self.message())
+         .field("file", &self.file())
+         .field("line", &self.line())
+         .finish()
+    }
+}
+
+impl fmt::Display for LastPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "panicked at '{}', {}:{}", self.message(), self.file(), self.line())
+    }
+}
+
+/// The last panic that happened before the board was reset, if any.
+///
+/// Every panic is stored in a part of RAM that is not cleared on startup, so it is still there
+/// after the board is reset by the watchdog, the reset button or `DefaultHook`. It is lost when the
+/// board loses power, unless a backup in EEPROM was set up with `set_eeprom_backup`.
+///
+/// **Note**: Only AVR boards keep the record in RAM across a reset. On other boards, which also
+/// have no EEPROM, only a panic since the last reset is returned.
+pub fn last_panic() -> Option<LastPanic> {
+    let record = LAST_PANIC.with(|record| *record);
+    if record.is_valid() {
+        return Some(LastPanic { record: record });
+    }
+
+    let address = EEPROM_BACKUP.with(|address| *address);
+    address.and_then(|address| {
+        let mut record = EMPTY_RECORD;
+        unsafe {
+            ffi::rduino_eeprom_read(address, record.as_bytes_mut().as_mut_ptr(), PANIC_RECORD_SIZE);
+        }
+        if record.is_valid() {
+            Some(LastPanic { record: record })
+        } else {
+            None
+        }
+    })
+}
+
+/// Forget the last panic, both in RAM and in the EEPROM backup.
+pub fn clear_last_panic() {
+    LAST_PANIC.with(|record| record.magic = 0);
+    if let Some(address) = EEPROM_BACKUP.with(|address| *address) {
+        unsafe {
+            ffi::rduino_eeprom_write(address, [0, 0].as_ptr(), 2);
+        }
+    }
+}
+
+/// Also store every panic in EEPROM, so `last_panic` survives a loss of power.
+///
+/// The backup takes `PANIC_RECORD_SIZE` bytes starting at the given address. It has to be set up
+/// on every startup, before calling `last_panic`.
+///
+/// # Errors
+/// Returns `InvalidAddress` if the backup does not fit in the EEPROM of the board.
+pub fn set_eeprom_backup(address: u16) -> io::Result<()> {
+    if address as usize + PANIC_RECORD_SIZE > unsafe { ffi::rduino_eeprom_length() } {
+        return Err(io::Error::InvalidAddress);
+    }
+    EEPROM_BACKUP.with(|backup| *backup = Some(address));
+    Ok(())
+}
+
+#[doc(hidden)]
+pub fn __record_panic(info: &PanicInfo) {
+    let mut record = EMPTY_RECORD;
+    record.magic = RECORD_MAGIC;
+    record.line = info.location.line;
+
+    let file = info.location.file;
+    let file_len = {
+        let mut file_buf = Truncate { buf: &mut record.file, len: 0 };
+        let _ = fmt::Write::write_str(&mut file_buf, file);
+        file_buf.len
+    };
+    record.file_len = file_len as u16;
+
+    let message_len = {
+        let mut message_buf = Truncate { buf: &mut record.message, len: 0 };
+        let _ = fmt::write(&mut message_buf, info.payload);
+        message_buf.len
+    };
+    record.message_len = message_len as u16;
+    record.checksum = record.checksum();
+
+    LAST_PANIC.with(|stored| *stored = record);
+    if let Some(address) = EEPROM_BACKUP.with(|address| *address) {
+        unsafe {
+            ffi::rduino_eeprom_write(address, record.as_bytes().as_ptr(), PANIC_RECORD_SIZE);
+        }
     }
 }
@@ -1,5 +1,18 @@
-use ffi::{self, RduinoAnalogReference, RduinoPinLevel, RduinoPinMode};
+use ffi::{self, RduinoAnalogReference, RduinoBitOrder, RduinoPinLevel, RduinoPinMode};
 use interrupts::ToInterrupt;
+use io;
+use platform::raw::c_int;
+use sys::statics::BoardLocal;
+use sys_common::pins::{IntoInterruptPin, IntoMode};
+use sys_common::timers;
+use time::Duration;
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::mem;
+
+/// The resolution in bits of all analog output pins.
+static WRITE_RESOLUTION: BoardLocal<u8> = BoardLocal::new(8);
 
 /// Digital pin state.
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
@@ -106,28 +119,179 @@ impl Default for AnalogReference {
 pub trait Pin {
     /// The *digital* number of this pin.
     fn number(&self) -> u8;
+}
+
+
+
+/// Mode of a digital pin that has not been configured yet.
+///
+/// The pin can neither be read nor written until it is converted into an input or output pin.
+#[derive(Debug)]
+pub enum Unconfigured {}
+
+/// Mode of a digital input pin.
+///
+/// The parameter is the internal resistor that is active, e.g. `Input<PullUp>`.
+#[derive(Debug)]
+pub struct Input<PULL = Floating> {
+    _pull: PhantomData<PULL>
+}
+
+/// An input without an internal resistor.
+///
+/// The level of a floating input is undefined unless it is driven by an external circuit.
+#[derive(Debug)]
+pub enum Floating {}
+
+/// An input with the internal pull-up resistor active.
+#[derive(Debug)]
+pub enum PullUp {}
+
+/// Mode of a digital output pin.
+#[derive(Debug)]
+pub enum Output {}
+
+
+
+/// A digital pin.
+///
+/// Digital pin can be used to read and write digital values (high and low). Pins are obtained
+/// from `Pins` and can be converted into other types of pins, e.g. with `into_pwm()`.
+///
+/// The mode of the pin is part of its type, so only input pins can be read and only output pins
+/// can be written. Converting the pin into another mode, e.g. with `into_output()`, configures
+/// the hardware accordingly.
+#[derive(Debug)]
+pub struct DigitalPin<MODE = Unconfigured> {
+    number: u8,
+    _mode: PhantomData<MODE>
+}
+
+impl<MODE> DigitalPin<MODE> {
+    /// Whether this pin can be used as a PWM output pin.
+    #[inline]
+    pub fn has_pwm(&self) -> bool {
+        unsafe { ffi::rduino_digital_pin_has_pwm(self.number) }
+    }
+
+    /// Use this pin as an input without an internal resistor.
+    #[inline]
+    pub fn into_floating_input(self) -> DigitalPin<Input<Floating>> {
+        self.into_mode(PinMode::Input)
+    }
+
+    /// Use this pin as an input with the internal pull-up resistor active.
+    #[inline]
+    pub fn into_pull_up_input(self) -> DigitalPin<Input<PullUp>> {
+        self.into_mode(PinMode::InputPullup)
+    }
 
-    /// Set the mode of this pin.
+    /// Use this pin as an output.
+    #[inline]
+    pub fn into_output(self) -> DigitalPin<Output> {
+        self.into_mode(PinMode::Output)
+    }
+
+    /// Use this pin as a PWM output pin if supported.
+    ///
+    /// Returns the software analog output pin if this pin supports it, otherwise the pin is given
+    /// back unchanged. Use `AnalogOutputPin::into_digital()` to convert it back.
     ///
-    /// The parameter of this method can either be a `PinMode` or a `PinModeExt` from the
-    /// platform-specific extensions if the platform supports it.
+    /// Pulse Width Module (PWM) can be used to simulate analog output on pins that are not
+    /// connected to a Digital-to-Analog Converter (DAC).
+    #[inline]
+    pub fn into_pwm(self) -> Result<AnalogOutputPin, DigitalPin<MODE>> {
+        if self.has_pwm() {
+            Ok(AnalogOutputPin::new(self.number, AnalogOutputKind::Software))
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Use this pin as an analog input pin if supported.
+    ///
+    /// Returns the analog input pin if this pin is connected to the ADC, otherwise the pin is
+    /// given back unchanged.
+    #[inline]
+    pub fn into_analog_input(self) -> Result<AnalogInputPin, DigitalPin<MODE>> {
+        if unsafe { ffi::RDUINO_ANALOG_INPUT_PINS.contains(&self.number) } {
+            Ok(AnalogInputPin { number: self.number })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<MODE> IntoMode for DigitalPin<MODE> {
     #[inline]
-    fn set_mode<M: Into<PinMode>>(&self, mode: M) {
+    fn into_mode<NEW>(self, mode: PinMode) -> DigitalPin<NEW> {
         unsafe {
-            ffi::rduino_pin_mode(self.number(), mode.into().to_ffi());
+            ffi::rduino_pin_mode(self.number, mode.to_ffi());
+        }
+        DigitalPin {
+            number: self.number,
+            _mode: PhantomData
         }
     }
 }
 
-/// A digital pin.
-///
-/// Digital pin can be used to read and write digital values (high and low).
-#[derive(Clone, Debug)]
-pub struct DigitalPin {
-    number: u8
+impl<PULL> DigitalPin<Input<PULL>> {
+    /// Get the value of this digital pin.
+    #[inline]
+    pub fn read(&self) -> PinLevel {
+        PinLevel::from_ffi(unsafe { ffi::rduino_digital_read(self.number) })
+    }
+
+    /// Measure the width of a pulse on this pin.
+    ///
+    /// Waits for the pin to go to the given level, then measures how long it stays there. If
+    /// `level` is `High`, this measures a low-high-low pulse. Returns `None` if no complete pulse
+    /// was seen within the timeout, which includes the time spent waiting for the pulse to start.
+    ///
+    /// **Note**: Interrupts occurring during the measurement may cause the result to be too short
+    /// on some platforms. Pulses from about 10 microseconds to 3 minutes can be measured, longer
+    /// timeouts are cut off at about 71 minutes.
+    ///
+    /// See also: [`pulseIn()`] from the Arduino reference.
+    ///
+    /// [`pulseIn()`]: https://www.arduino.cc/en/Reference/PulseIn
+    #[inline]
+    pub fn measure_pulse(&self, level: PinLevel, timeout: Duration) -> Option<Duration> {
+        let timeout_us = timeout.as_micros().min(u32::max_value() as u64) as u32;
+        let width_us = unsafe { ffi::rduino_pulse_in(self.number, level.to_ffi(), timeout_us) };
+        if width_us > 0 {
+            Some(Duration::from_micros(width_us as u64))
+        } else {
+            None
+        }
+    }
+
+    /// Use this pin as an external interrupt if supported.
+    ///
+    /// Returns the interrupt pin if this pin can trigger an external interrupt, otherwise the pin
+    /// is given back unchanged. The mode of the pin is not changed.
+    #[inline]
+    pub fn into_interrupt(self) -> Result<InterruptPin<PULL>, DigitalPin<Input<PULL>>> {
+        let interrupt = unsafe { ffi::rduino_digital_pin_to_interrupt(self.number) };
+        self.into_interrupt_pin(interrupt)
+    }
+}
+
+impl<PULL> IntoInterruptPin<PULL> for DigitalPin<Input<PULL>> {
+    #[inline]
+    fn into_interrupt_pin(self, interrupt: c_int) -> Result<InterruptPin<PULL>, Self> {
+        if interrupt >= 0 {
+            Ok(InterruptPin {
+                pin: self,
+                interrupt: interrupt as u8
+            })
+        } else {
+            Err(self)
+        }
+    }
 }
 
-impl DigitalPin {
+impl DigitalPin<Output> {
     /// Set the digital value of this pin.
     #[inline]
     pub fn write(&self, value: PinLevel) {
@@ -136,67 +300,145 @@ impl DigitalPin {
         }
     }
 
-    /// Get the value of this digital pin.
+    /// Get the value of this pin.
+    ///
+    /// This is the level on the pin, which differs from the level it is set to when a load pulls
+    /// the pin the other way.
     #[inline]
     pub fn read(&self) -> PinLevel {
         PinLevel::from_ffi(unsafe { ffi::rduino_digital_read(self.number) })
     }
 
-    /// Whether this pin can be used as a PWM output pin.
+    /// Get the value this pin is set to, as last written with `write`.
     #[inline]
-    pub fn has_pwm(&self) -> bool {
-        unsafe { ffi::rduino_digital_pin_has_pwm(self.number) }
+    pub fn read_output(&self) -> PinLevel {
+        PinLevel::from_ffi(unsafe { ffi::rduino_digital_read_output(self.number) })
     }
 
-    /// Use this pin as a PWM output pin if supported.
+    /// Generate a square wave with the given frequency in hertz on this pin.
     ///
-    /// Returns the software analog output pin if this pin supports it.
+    /// If a duration in milliseconds is given the tone stops by itself after that time, otherwise
+    /// it continues until the returned guard is dropped. Either way the tone generator stays
+    /// reserved for this pin until the guard is dropped. The guard borrows the pin, so the pin can
+    /// not be converted or released while the tone is playing.
     ///
-    /// Pulse Width Module (PWM) can be used to simulate analog output on pins that are not
-    /// connected to a Digital-to-Analog Converter (DAC).
-    #[inline]
-    pub fn to_pwm(&self) -> Option<AnalogOutputPin> {
-        if self.has_pwm() {
-            Some(AnalogOutputPin {
-                number: self.number,
-                kind: AnalogOutputKind::Software
-            })
-        } else {
-            None
+    /// **Note**: The tone generator interferes with PWM output on some pins. On the Arduino Due
+    /// this method has no effect.
+    ///
+    /// See also: [`tone()`] from the Arduino reference.
+    ///
+    /// # Errors
+    /// Returns `ToneBusy` if a tone is already being generated, since only one pin can generate a
+    /// tone at a time, or if an ISR is attached to the timer that generates tones, such as `Timer2`
+    /// on AVR.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine. Because the
+    /// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid
+    /// an unsafe API.
+    ///
+    /// [`tone()`]: https://www.arduino.cc/en/Reference/Tone
+    pub fn tone<'a>(&'a self, frequency: u16, duration_ms: Option<u32>)
+                    -> io::Result<ToneGuard<'a>> {
+        check_isr!("DigitalPin::tone");
+
+        timers::claim_tone()?;
+        unsafe {
+            ffi::rduino_tone(self.number, frequency as _, duration_ms.unwrap_or(0));
         }
+        Ok(ToneGuard { number: self.number, _pin: PhantomData })
     }
 }
 
-impl Pin for DigitalPin {
+impl<MODE> Pin for DigitalPin<MODE> {
     #[inline]
     fn number(&self) -> u8 {
         self.number
     }
 }
 
-impl ToInterrupt for DigitalPin {
-    fn to_interrupt(self) -> Option<u8> {
-        ToInterrupt::to_interrupt(&self)
+
+
+/// A digital pin that serves as an external interrupt.
+///
+/// Created by `DigitalPin::into_interrupt()` or, on platforms that support pin change interrupts,
+/// by the `into_pin_change_interrupt()` method of the platform-specific `DigitalPinExt` trait. Pin
+/// change interrupts only support `InterruptMode::Change`. A reference to this pin can be passed to
+/// `InterruptServiceRoutine::attach`. The level of the pin can still be read, but its mode can not
+/// be changed until it is converted back with `into_digital()`.
+#[derive(Debug)]
+pub struct InterruptPin<PULL = Floating> {
+    pin: DigitalPin<Input<PULL>>,
+    interrupt: u8
+}
+
+impl<PULL> InterruptPin<PULL> {
+    /// Get the value of this digital pin.
+    #[inline]
+    pub fn read(&self) -> PinLevel {
+        self.pin.read()
+    }
+
+    /// Stop using this pin as an external interrupt.
+    #[inline]
+    pub fn into_digital(self) -> DigitalPin<Input<PULL>> {
+        self.pin
+    }
+}
+
+impl<PULL> Pin for InterruptPin<PULL> {
+    #[inline]
+    fn number(&self) -> u8 {
+        self.pin.number
     }
 }
 
-impl<'a> ToInterrupt for &'a DigitalPin {
+impl<'a, PULL> ToInterrupt for &'a InterruptPin<PULL> {
+    #[inline]
     fn to_interrupt(self) -> Option<u8> {
-        let interrupt = unsafe { ffi::rduino_digital_pin_to_interrupt(self.number) };
-        if interrupt >= 0 {
-            Some(interrupt as u8)
-        } else {
-            None
+        Some(self.interrupt)
+    }
+}
+
+
+
+/// A RAII implementation of a tone generated on a digital pin.
+///
+/// When this structure is dropped or `stop` is called, the tone is stopped and the tone generator
+/// becomes available again. The guard borrows the pin for its lifetime `'a`.
+#[must_use]
+#[derive(Debug)]
+pub struct ToneGuard<'a> {
+    number: u8,
+    _pin: PhantomData<&'a DigitalPin<Output>>
+}
+
+impl<'a> ToneGuard<'a> {
+    /// Stop the tone.
+    ///
+    /// This has the same effect as dropping the guard.
+    pub fn stop(self) {
+        mem::drop(self)
+    }
+}
+
+impl<'a> Drop for ToneGuard<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::rduino_no_tone(self.number);
         }
+        timers::release_tone();
     }
 }
 
+impl<'a> !Send for ToneGuard<'a> {}
+
 
 
 /// An analog input pin.
 ///
 /// Analog input pins can be used to read analog values through an Analog-to-Digital Converter (ADC).
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct AnalogInputPin {
     number: u8
 }
@@ -213,10 +455,13 @@ impl AnalogInputPin {
     }
 
     /// Set the resolution in number of bits for *all* analog input pins.
+    ///
+    /// Resolutions above 16 bits are clamped, because larger values do not fit in the `u16` that
+    /// is returned by `read()`.
     #[inline]
     pub fn set_resolution(bits: u8) {
         unsafe {
-            ffi::rduino_analog_read_resolution(bits);
+            ffi::rduino_analog_read_resolution(bits.min(16));
         }
     }
 
@@ -230,6 +475,19 @@ impl AnalogInputPin {
             ffi::rduino_analog_reference(reference.into().to_ffi());
         }
     }
+
+    /// Use this pin as a digital pin if supported.
+    ///
+    /// Returns the digital pin if this pin can also be used for digital I/O, otherwise the pin is
+    /// given back unchanged.
+    #[inline]
+    pub fn into_digital(self) -> Result<DigitalPin, AnalogInputPin> {
+        if (self.number as u32) < ffi::RDUINO_NUM_DIGITAL_PINS {
+            Ok(DigitalPin { number: self.number, _mode: PhantomData })
+        } else {
+            Err(self)
+        }
+    }
 }
 
 impl Pin for AnalogInputPin {
@@ -245,13 +503,25 @@ impl Pin for AnalogInputPin {
 ///
 /// Analog output pins can be used to write analog values. The pin can either be a hardware analog
 /// output using a DAC or a software analog output using PWM.
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct AnalogOutputPin {
     number: u8,
-    kind: AnalogOutputKind
+    kind: AnalogOutputKind,
+    value: Cell<u16>,
+    enabled: Cell<bool>
 }
 
 impl AnalogOutputPin {
+    #[inline]
+    fn new(number: u8, kind: AnalogOutputKind) -> AnalogOutputPin {
+        AnalogOutputPin {
+            number: number,
+            kind: kind,
+            value: Cell::new(0),
+            enabled: Cell::new(true)
+        }
+    }
+
     /// The type of this pin (software or hardware).
     #[inline]
     pub fn kind(&self) -> AnalogOutputKind {
@@ -263,20 +533,82 @@ impl AnalogOutputPin {
     /// The value must be between `0` (inclusive) to `2^n` (exclusive) where `n` is the current
     /// digital write resolution (see `set_resolution()`). Any values exceeding the maximum value
     /// are clamped.
+    ///
+    /// If the pin is disabled, the value is applied once it is enabled again.
     #[inline]
     pub fn write(&self, value: u16) {
+        self.value.set(value);
+        if self.enabled.get() {
+            unsafe {
+                ffi::rduino_analog_write(self.number, value);
+            }
+        }
+    }
+
+    /// The value last written to this pin.
+    #[inline]
+    pub fn value(&self) -> u16 {
+        self.value.get()
+    }
+
+    /// Whether this pin is enabled.
+    ///
+    /// Pins are enabled when they are obtained.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Enable this pin, applying the value last written to it.
+    #[inline]
+    pub fn enable(&self) {
+        self.enabled.set(true);
+        self.write(self.value.get());
+    }
+
+    /// Disable this pin, setting its output to zero.
+    ///
+    /// The value written to the pin is remembered and applied again by `enable()`.
+    #[inline]
+    pub fn disable(&self) {
+        self.enabled.set(false);
         unsafe {
-            ffi::rduino_analog_write(self.number, value);
+            ffi::rduino_analog_write(self.number, 0);
         }
     }
 
     /// Set the resolution in number of bits for *all* analog output pins.
+    ///
+    /// Resolutions above 16 bits are clamped, because larger values do not fit in the `u16` that
+    /// is accepted by `write()`.
     #[inline]
     pub fn set_resolution(bits: u8) {
+        let bits = bits.min(16);
+        WRITE_RESOLUTION.with(|resolution| *resolution = bits);
         unsafe {
             ffi::rduino_analog_write_resolution(bits);
         }
     }
+
+    /// The largest value that can be written with the current resolution.
+    #[inline]
+    pub fn max_value() -> u16 {
+        ((1u32 << WRITE_RESOLUTION.with(|resolution| *resolution)) - 1) as u16
+    }
+
+    /// Stop using this pin as a PWM output pin.
+    ///
+    /// Returns the digital output pin if this is a software analog output pin. Hardware analog
+    /// output pins are given back unchanged.
+    #[inline]
+    pub fn into_digital(self) -> Result<DigitalPin<Output>, AnalogOutputPin> {
+        match self.kind {
+            AnalogOutputKind::Software => {
+                Ok(DigitalPin { number: self.number, _mode: PhantomData })
+            }
+            AnalogOutputKind::Hardware => Err(self)
+        }
+    }
 }
 
 impl Pin for AnalogOutputPin {
@@ -300,52 +632,272 @@ pub enum AnalogOutputKind {
 
 
 
-/// Obtain a handle to the digital pin with the given number if it exists.
-///
-/// # Panics
-/// Panics if this function is called from inside an interrupt service routine. Because the
-/// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid an
-/// unsafe API.
-#[inline]
-pub fn digital_pin(number: u8) -> Option<DigitalPin> {
-    check_isr!("digital_pin");
+/// The order in which the bits of a byte are shifted.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum BitOrder {
+    /// The most significant bit is shifted first.
+    MsbFirst,
+
+    /// The least significant bit is shifted first.
+    LsbFirst
+}
 
-    if number as u32 >= ffi::RDUINO_NUM_DIGITAL_PINS {
-        return None;
+impl BitOrder {
+    #[inline]
+    fn to_ffi(self) -> RduinoBitOrder {
+        match self {
+            BitOrder::MsbFirst => RduinoBitOrder::MsbFirst,
+            BitOrder::LsbFirst => RduinoBitOrder::LsbFirst
+        }
     }
+}
 
-    Some(DigitalPin { number: number })
+impl Default for BitOrder {
+    #[inline]
+    fn default() -> BitOrder {
+        BitOrder::MsbFirst
+    }
 }
 
-/// Obtain a handle to the analog input pin with the given number if it exists.
+
+
+/// A bit-banged shift register.
+///
+/// Shift registers, such as the 74HC595 (serial-in, parallel-out) or the 74HC165 (parallel-in,
+/// serial-out), are driven with a data pin, a clock pin and optionally a latch pin. Multiple
+/// registers can be daisy-chained and accessed with a single multi-byte transfer.
+///
+/// Bytes can also be written using `io::Write`, e.g. with `write!`. Since `write!` may call
+/// `write` several times for a single message, the bytes are only shifted out by `write` and
+/// latched by `flush`, so the outputs never show a partially written message.
 ///
-/// On Arduino these pins are named `An` where `n` is the analog input number (e.g. `A3`).
+/// See also: [`shiftOut()`] and [`shiftIn()`] from the Arduino reference.
 ///
-/// # Panics
-/// Panics if this function is called from inside an interrupt service routine. Because the
-/// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid an
-/// unsafe API.
-#[inline]
-pub fn analog_input_pin(number: u8) -> Option<AnalogInputPin> {
-    check_isr!("analog_input_pin");
+/// [`shiftOut()`]: https://www.arduino.cc/en/Reference/ShiftOut
+/// [`shiftIn()`]: https://www.arduino.cc/en/Reference/ShiftIn
+#[derive(Debug)]
+pub struct ShiftRegister {
+    data: DigitalPin,
+    clock: DigitalPin<Output>,
+    latch: Option<DigitalPin<Output>>,
+    bit_order: BitOrder
+}
+
+impl ShiftRegister {
+    /// Create a new shift register using the given pins.
+    ///
+    /// The mode of the data pin is set on every transfer, so the same pins can be shared by an
+    /// input and an output register. Bits are shifted most significant bit first by default.
+    pub fn new<MODE>(data: DigitalPin<MODE>, clock: DigitalPin<Output>,
+                     latch: Option<DigitalPin<Output>>) -> ShiftRegister {
+        let data = DigitalPin { number: data.number, _mode: PhantomData };
+        clock.write(PinLevel::Low);
+        if let Some(ref latch) = latch {
+            latch.write(PinLevel::High);
+        }
 
-    let number = unsafe { ffi::RDUINO_ANALOG_INPUT_PINS.get(number as usize).cloned() };
-    number.map(|number| AnalogInputPin { number: number })
+        ShiftRegister {
+            data: data,
+            clock: clock,
+            latch: latch,
+            bit_order: BitOrder::default()
+        }
+    }
+
+    /// Set the order in which bits are shifted.
+    #[inline]
+    pub fn bit_order(mut self, bit_order: BitOrder) -> ShiftRegister {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Shift out a single byte and latch it.
+    #[inline]
+    pub fn write_byte(&self, byte: u8) {
+        self.write_bytes(&[byte]);
+    }
+
+    /// Shift out some bytes and latch them.
+    ///
+    /// For a chain of serial-in, parallel-out registers such as the 74HC595, the latch pin (`RCLK`)
+    /// is pulled low during the transfer and released afterwards, updating all outputs at once. The
+    /// first byte ends up in the register furthest down the chain.
+    pub fn write_bytes(&self, buf: &[u8]) {
+        if let Some(ref latch) = self.latch {
+            latch.write(PinLevel::Low);
+        }
+        self.shift_out(buf);
+        if let Some(ref latch) = self.latch {
+            latch.write(PinLevel::High);
+        }
+    }
+
+    /// Latch the inputs and shift in a single byte.
+    #[inline]
+    pub fn read_byte(&self) -> u8 {
+        let mut buf = [0];
+        self.read_bytes(&mut buf);
+        buf[0]
+    }
+
+    /// Latch the inputs and shift in enough bytes to fill the given buffer.
+    ///
+    /// For a chain of parallel-in, serial-out registers such as the 74HC165, the latch pin (`PL`)
+    /// is pulsed low to load the inputs before shifting. The first byte is read from the register
+    /// closest to the data pin.
+    pub fn read_bytes(&self, buf: &mut [u8]) {
+        self.set_data_mode(PinMode::Input);
+        // `shiftIn()` raises the clock before reading each bit, and the register shifts on that
+        // edge. The clock is raised before loading, so the first bit is read before any shift.
+        self.clock.write(PinLevel::High);
+        if let Some(ref latch) = self.latch {
+            latch.write(PinLevel::Low);
+            latch.write(PinLevel::High);
+        }
+
+        let bit_order = self.bit_order.to_ffi();
+        for byte in buf {
+            *byte = unsafe { ffi::rduino_shift_in(self.data.number, self.clock.number, bit_order) };
+        }
+        self.clock.write(PinLevel::Low);
+    }
+
+    /// Release the pins used by this shift register.
+    ///
+    /// Returns the data, clock and latch pins in that order. The mode of the data pin is left
+    /// unconfigured, since it changes with every transfer.
+    #[inline]
+    pub fn into_pins(self) -> (DigitalPin, DigitalPin<Output>, Option<DigitalPin<Output>>) {
+        (self.data, self.clock, self.latch)
+    }
+
+    fn shift_out(&self, buf: &[u8]) {
+        self.set_data_mode(PinMode::Output);
+        let bit_order = self.bit_order.to_ffi();
+        for &byte in buf {
+            unsafe {
+                ffi::rduino_shift_out(self.data.number, self.clock.number, bit_order, byte);
+            }
+        }
+    }
+
+    #[inline]
+    fn set_data_mode(&self, mode: PinMode) {
+        unsafe {
+            ffi::rduino_pin_mode(self.data.number, mode.to_ffi());
+        }
+    }
 }
 
-/// Obtain a handle to the *hardware* analog output pin with the given number if it exists.
-///
-/// On Arduino these pins are named `DACn` where `n` is the analog output number (e.g. `DAC0`). The
-/// returned pin is always of the hardware (DAC) type.
+impl io::Write for ShiftRegister {
+    /// Shift out some bytes without latching them.
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.shift_out(buf);
+        Ok(buf.len())
+    }
+
+    /// Latch the bytes shifted out since the last flush.
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(ref latch) = self.latch {
+            latch.write(PinLevel::Low);
+            latch.write(PinLevel::High);
+        }
+        Ok(())
+    }
+}
+
+
+
+/// The pins of the board.
 ///
-/// # Panics
-/// Panics if this function is called from inside an interrupt service routine. Because the
-/// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid an
-/// unsafe API.
-#[inline]
-pub fn analog_output_pin(number: u8) -> Option<AnalogOutputPin> {
-    check_isr!("analog_output_pin");
-
-    let number = unsafe { ffi::RDUINO_ANALOG_OUTPUT_PINS.get(number as usize).cloned() };
-    number.map(|number| AnalogOutputPin { number: number, kind: AnalogOutputKind::Hardware })
+/// This singleton is taken once at startup and hands out every pin at most once, so no two drivers
+/// can end up using the same physical pin. Analog input and output pins share their numbering with
+/// the digital pins, e.g. on the Arduino Uno analog input `A0` and digital pin 14 are the same pin
+/// and can only be taken once between them. A pin that is no longer needed can be given back with
+/// `release()`.
+#[derive(Debug)]
+pub struct Pins {
+    taken: [u8; 32]
+}
+
+impl Pins {
+    /// Take the pins of the board.
+    ///
+    /// Returns `None` if the pins were already taken before.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine. Because the
+    /// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid
+    /// an unsafe API.
+    pub fn take() -> Option<Pins> {
+        check_isr!("Pins::take");
+
+        if claim_pins() {
+            Some(Pins { taken: [0; 32] })
+        } else {
+            None
+        }
+    }
+
+    /// Take the digital pin with the given number.
+    ///
+    /// Returns `None` if the pin does not exist or was already taken.
+    pub fn digital(&mut self, number: u8) -> Option<DigitalPin> {
+        if number as u32 >= ffi::RDUINO_NUM_DIGITAL_PINS || !self.claim(number) {
+            return None;
+        }
+
+        Some(DigitalPin { number: number, _mode: PhantomData })
+    }
+
+    /// Take the analog input pin with the given number.
+    ///
+    /// On Arduino these pins are named `An` where `n` is the analog input number (e.g. `A3`).
+    /// Returns `None` if the pin does not exist or was already taken.
+    pub fn analog_input(&mut self, number: u8) -> Option<AnalogInputPin> {
+        let number = unsafe { ffi::RDUINO_ANALOG_INPUT_PINS.get(number as usize).cloned() };
+        match number {
+            Some(number) if self.claim(number) => Some(AnalogInputPin { number: number }),
+            _ => None
+        }
+    }
+
+    /// Take the *hardware* analog output pin with the given number.
+    ///
+    /// On Arduino these pins are named `DACn` where `n` is the analog output number (e.g. `DAC0`).
+    /// The returned pin is always of the hardware (DAC) type. Returns `None` if the pin does not
+    /// exist or was already taken.
+    pub fn analog_output(&mut self, number: u8) -> Option<AnalogOutputPin> {
+        let number = unsafe { ffi::RDUINO_ANALOG_OUTPUT_PINS.get(number as usize).cloned() };
+        match number {
+            Some(number) if self.claim(number) => {
+                Some(AnalogOutputPin::new(number, AnalogOutputKind::Hardware))
+            }
+            _ => None
+        }
+    }
+
+    /// Give back a pin so it can be taken again.
+    #[inline]
+    pub fn release<P: Pin>(&mut self, pin: P) {
+        let number = pin.number();
+        self.taken[number as usize / 8] &= !(1 << (number % 8));
+    }
+
+    fn claim(&mut self, number: u8) -> bool {
+        let (index, mask) = (number as usize / 8, 1 << (number % 8));
+        if self.taken[index] & mask != 0 {
+            return false;
+        }
+        self.taken[index] |= mask;
+        true
+    }
+}
+
+fn claim_pins() -> bool {
+    static TAKEN: BoardLocal<bool> = BoardLocal::new(false);
+
+    TAKEN.with(|taken| !mem::replace(taken, true))
 }
@@ -0,0 +1,34 @@
+//! AVR-specific extensions to the `rduino::interrupts` module.
+
+use ffi::RduinoTimer;
+use interrupts::Timer;
+
+/// The AVR hardware timers that can generate periodic interrupts.
+///
+/// Can be used with `InterruptServiceRoutine::attach_timer`.
+///
+/// **Note**: The Arduino core uses these timers for PWM output, so attaching an ISR to a timer
+/// disturbs `analogWrite` on the pins it drives, e.g. pins 9 and 10 for `Timer1` and pins 3 and
+/// 11 for `Timer2` on the Arduino Uno. `Timer2` also generates tones, so attaching to it fails with
+/// `TimerBusy` while a tone is playing, and `DigitalPin::tone` fails with `ToneBusy` while an ISR
+/// is attached to it.
+///
+/// **Note**: Not all timers are available on all AVR boards. Attaching to a missing timer fails.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum TimerExt {
+    /// The 16-bit timer 1, for frequencies from 1 Hz up to the system clock.
+    Timer1,
+
+    /// The 8-bit timer 2, for frequencies from about 61 Hz (at 16 MHz) up to the system clock.
+    Timer2
+}
+
+impl From<TimerExt> for Timer {
+    #[inline]
+    fn from(timer: TimerExt) -> Timer {
+        match timer {
+            TimerExt::Timer1 => Timer::__Raw(RduinoTimer::Timer1),
+            TimerExt::Timer2 => Timer::__Raw(RduinoTimer::Timer2)
+        }
+    }
+}
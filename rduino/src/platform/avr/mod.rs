@@ -1,3 +1,4 @@
 //! AVR-specific extensions.
 
+pub mod interrupts;
 pub mod pins;
@@ -1,7 +1,13 @@
 //! AVR-specific extensions to the `rduino::pins` module.
 
+#[cfg(feature = "pin-change-interrupts")]
+use ffi;
 use ffi::RduinoAnalogReference;
 use pins::AnalogReference;
+#[cfg(feature = "pin-change-interrupts")]
+use pins::{DigitalPin, Input, InterruptPin, Pin};
+#[cfg(feature = "pin-change-interrupts")]
+use sys_common::pins::IntoInterruptPin;
 
 /// Additional AVR-specific analog reference modes.
 ///
@@ -68,3 +74,32 @@ impl From<AnalogReferenceExt> for AnalogReference {
         AnalogReference::__Raw(raw)
     }
 }
+
+/// Additional AVR-specific digital pin functionality.
+///
+/// Only available with the `pin-change-interrupts` feature, which defines the pin change interrupt
+/// vectors. The SoftwareSerial library defines these vectors as well, so a program using it
+/// together with this feature fails to link.
+#[cfg(feature = "pin-change-interrupts")]
+pub trait DigitalPinExt<PULL>: Sized {
+    /// Use this pin as a pin change interrupt if supported.
+    ///
+    /// Unlike external interrupts, which are only available on a few pins, pin change interrupts
+    /// are available on most pins of most AVR boards. They only support `InterruptMode::Change`.
+    /// Returns the interrupt pin if this pin supports it, otherwise the pin is given back
+    /// unchanged. The mode of the pin is not changed.
+    ///
+    /// **Note**: The pins are grouped by port and every group shares a single interrupt vector, so
+    /// very short pulses on one pin may be missed while the routine of another pin in the same
+    /// group is running.
+    fn into_pin_change_interrupt(self) -> Result<InterruptPin<PULL>, Self>;
+}
+
+#[cfg(feature = "pin-change-interrupts")]
+impl<PULL> DigitalPinExt<PULL> for DigitalPin<Input<PULL>> {
+    #[inline]
+    fn into_pin_change_interrupt(self) -> Result<InterruptPin<PULL>, Self> {
+        let interrupt = unsafe { ffi::rduino_digital_pin_to_pin_change_interrupt(self.number()) };
+        self.into_interrupt_pin(interrupt)
+    }
+}
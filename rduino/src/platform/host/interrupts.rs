@@ -0,0 +1,28 @@
+//! Simulated board extensions to the `rduino::interrupts` module.
+
+use ffi::RduinoTimer;
+use interrupts::Timer;
+
+/// The hardware timers of the simulated board.
+///
+/// Can be used with `InterruptServiceRoutine::attach_timer`. The timers accept the same
+/// frequencies as the ones on the Arduino Uno and fire as the simulated clock advances.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum TimerExt {
+    /// A 16-bit timer, for frequencies from 1 Hz up to 16 MHz.
+    Timer1,
+
+    /// An 8-bit timer, for frequencies from 61 Hz up to 16 MHz. Like on the Arduino Uno, it also
+    /// generates tones.
+    Timer2
+}
+
+impl From<TimerExt> for Timer {
+    #[inline]
+    fn from(timer: TimerExt) -> Timer {
+        match timer {
+            TimerExt::Timer1 => Timer::__Raw(RduinoTimer::Timer1),
+            TimerExt::Timer2 => Timer::__Raw(RduinoTimer::Timer2)
+        }
+    }
+}
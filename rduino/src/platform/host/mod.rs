@@ -0,0 +1,6 @@
+//! Extensions for the simulated board.
+//!
+//! This module is only available with the `host-sim` feature.
+
+pub mod interrupts;
+pub mod pins;
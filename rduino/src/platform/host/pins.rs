@@ -0,0 +1,24 @@
+//! Simulated board extensions to the `rduino::pins` module.
+
+use ffi;
+use pins::{DigitalPin, Input, InterruptPin, Pin};
+use sys_common::pins::IntoInterruptPin;
+
+/// Additional digital pin functionality of the simulated board.
+///
+/// This mirrors the `DigitalPinExt` trait of the AVR-specific extensions.
+pub trait DigitalPinExt<PULL>: Sized {
+    /// Use this pin as a pin change interrupt.
+    ///
+    /// Every pin of the simulated board supports pin change interrupts, which only support
+    /// `InterruptMode::Change`. The mode of the pin is not changed.
+    fn into_pin_change_interrupt(self) -> Result<InterruptPin<PULL>, Self>;
+}
+
+impl<PULL> DigitalPinExt<PULL> for DigitalPin<Input<PULL>> {
+    #[inline]
+    fn into_pin_change_interrupt(self) -> Result<InterruptPin<PULL>, Self> {
+        let interrupt = unsafe { ffi::rduino_digital_pin_to_pin_change_interrupt(self.number()) };
+        self.into_interrupt_pin(interrupt)
+    }
+}
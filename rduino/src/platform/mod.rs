@@ -4,6 +4,9 @@ pub mod avr;
 #[cfg(arduino_arch = "samd")]
 pub mod samd;
 
+#[cfg(feature = "host-sim")]
+pub mod host;
+
 #[doc(inline)]
 pub mod raw {
     //! Raw platform-specific types.
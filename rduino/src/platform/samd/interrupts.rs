@@ -1,7 +1,11 @@
 //! SAMD-specific extensions to the `rduino::interrupts` module.
 
 use ffi::RduinoInterruptMode;
+#[cfg(feature = "samd-timers")]
+use ffi::RduinoTimer;
 use interrupts::InterruptMode;
+#[cfg(feature = "samd-timers")]
+use interrupts::Timer;
 
 /// Additional SAMD-specific interrupt modes.
 ///
@@ -18,3 +22,33 @@ impl From<InterruptModeExt> for InterruptMode {
         InterruptMode::__Raw(RduinoInterruptMode::High)
     }
 }
+
+/// The SAMD timer/counters that can generate periodic interrupts.
+///
+/// Can be used with `InterruptServiceRoutine::attach_timer`. Both are 16-bit counters clocked from
+/// the 48 MHz main clock, covering frequencies from 1 Hz up to the main clock.
+///
+/// Only available with the `samd-timers` feature, which defines the `TC3_Handler` and
+/// `TC4_Handler` interrupt handlers. The Servo library defines `TC4_Handler` as well and some
+/// cores use TC3 or TC4 to implement `tone()`, so a program using either of them together with
+/// this feature fails to link.
+#[cfg(feature = "samd-timers")]
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub enum TimerExt {
+    /// Timer/counter 3.
+    Tc3,
+
+    /// Timer/counter 4.
+    Tc4
+}
+
+#[cfg(feature = "samd-timers")]
+impl From<TimerExt> for Timer {
+    #[inline]
+    fn from(timer: TimerExt) -> Timer {
+        match timer {
+            TimerExt::Tc3 => Timer::__Raw(RduinoTimer::Tc3),
+            TimerExt::Tc4 => Timer::__Raw(RduinoTimer::Tc4)
+        }
+    }
+}
@@ -1,11 +1,12 @@
 //! SAMD-specific extensions to the `rduino::pins` module.
 
 use ffi::{RduinoAnalogReference, RduinoPinMode};
-use pins::{AnalogReference, PinMode};
+use pins::{AnalogReference, DigitalPin, Input, PinMode};
+use sys_common::pins::IntoMode;
 
 /// Additional SAMD-specific pin modes.
 ///
-/// Can be used with `Pin::set_mode`.
+/// Can be converted into a `PinMode`.
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
 pub enum PinModeExt {
     /// Use the internal pulldown resistor.
@@ -19,6 +20,23 @@ impl From<PinModeExt> for PinMode {
     }
 }
 
+/// An input with the internal pull-down resistor active.
+#[derive(Debug)]
+pub enum PullDown {}
+
+/// Additional SAMD-specific digital pin modes.
+pub trait DigitalPinExt {
+    /// Use this pin as an input with the internal pull-down resistor active.
+    fn into_pull_down_input(self) -> DigitalPin<Input<PullDown>>;
+}
+
+impl<MODE> DigitalPinExt for DigitalPin<MODE> {
+    #[inline]
+    fn into_pull_down_input(self) -> DigitalPin<Input<PullDown>> {
+        self.into_mode(PinModeExt::InputPulldown.into())
+    }
+}
+
 /// Additional SAMD-specific analog reference modes.
 ///
 /// Can be used with `AnalogPin::set_reference`.
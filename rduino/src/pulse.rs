@@ -0,0 +1,203 @@
+//! Helpers for common devices that communicate through pulse widths.
+//!
+//! All of these are built on `DigitalPin::measure_pulse` and share its caveats: measurements block
+//! until a pulse is seen or the timeout expires, and interrupts occurring during a measurement may
+//! distort the result.
+
+use pins::{DigitalPin, Input, Output, PinLevel};
+use time::{self, Duration};
+
+/// Speed of sound in air at 20 °C in micrometers per microsecond.
+const SPEED_OF_SOUND: u64 = 343;
+
+/// An ultrasonic distance sensor such as the HC-SR04.
+///
+/// The sensor is started with a short pulse on the trigger pin, after which it answers with a
+/// pulse on the echo pin that is as long as the sound took to travel to the nearest object and
+/// back.
+#[derive(Debug)]
+pub struct Ultrasonic {
+    trigger: DigitalPin<Output>,
+    echo: DigitalPin<Input>,
+    timeout: Duration
+}
+
+impl Ultrasonic {
+    /// Create a new ultrasonic sensor using the given trigger and echo pins.
+    ///
+    /// The default time to wait for an echo is 30 milliseconds, which corresponds to a distance of
+    /// about 5 meters.
+    pub fn new(trigger: DigitalPin<Output>, echo: DigitalPin<Input>) -> Ultrasonic {
+        trigger.write(PinLevel::Low);
+
+        Ultrasonic {
+            trigger: trigger,
+            echo: echo,
+            timeout: Duration::from_millis(30)
+        }
+    }
+
+    /// Set the time to wait for an echo.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Ultrasonic {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a ping and measure the duration of the echo.
+    ///
+    /// Returns `None` if no echo was received in time.
+    pub fn ping(&self) -> Option<Duration> {
+        self.trigger.write(PinLevel::Low);
+        time::sleep_microsecs(2);
+        self.trigger.write(PinLevel::High);
+        time::sleep_microsecs(10);
+        self.trigger.write(PinLevel::Low);
+
+        self.echo.measure_pulse(PinLevel::High, self.timeout)
+    }
+
+    /// Measure the distance to the nearest object in millimeters.
+    ///
+    /// Returns `None` if no object was detected within range.
+    #[inline]
+    pub fn distance_mm(&self) -> Option<u32> {
+        self.ping().map(|echo| (echo.as_micros() * SPEED_OF_SOUND / 2000) as u32)
+    }
+
+    /// Release the pins used by this sensor.
+    ///
+    /// Returns the trigger and echo pins in that order.
+    #[inline]
+    pub fn into_pins(self) -> (DigitalPin<Output>, DigitalPin<Input>) {
+        (self.trigger, self.echo)
+    }
+}
+
+
+
+/// A measured PWM signal.
+///
+/// Created by the `measure_pwm` function.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct PwmMeasurement {
+    high: Duration,
+    low: Duration
+}
+
+impl PwmMeasurement {
+    /// The time the signal is high.
+    #[inline]
+    pub fn high(&self) -> Duration {
+        self.high
+    }
+
+    /// The time the signal is low.
+    #[inline]
+    pub fn low(&self) -> Duration {
+        self.low
+    }
+
+    /// The period of the signal.
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.high + self.low
+    }
+
+    /// The frequency of the signal in hertz.
+    #[inline]
+    pub fn frequency(&self) -> u32 {
+        (1_000_000 / self.period().as_micros()) as u32
+    }
+
+    /// The fraction of the period the signal is high, between `0.0` and `1.0`.
+    #[inline]
+    pub fn duty_cycle(&self) -> f32 {
+        self.high.as_micros() as f32 / self.period().as_micros() as f32
+    }
+}
+
+/// Measure a PWM signal on the given pin by measuring a high and a low pulse.
+///
+/// The timeout applies to each of the two pulses separately. Returns `None` if either pulse was not
+/// seen in time, which is also the case for signals with a duty cycle of 0% or 100%.
+pub fn measure_pwm<PULL>(pin: &DigitalPin<Input<PULL>>, timeout: Duration)
+                         -> Option<PwmMeasurement> {
+    let high = match pin.measure_pulse(PinLevel::High, timeout) {
+        Some(high) => high,
+        None => return None
+    };
+    pin.measure_pulse(PinLevel::Low, timeout).map(|low| {
+        PwmMeasurement {
+            high: high,
+            low: low
+        }
+    })
+}
+
+
+
+/// A channel of a hobby RC receiver.
+///
+/// RC receivers output a high pulse for every channel about 50 times per second. The pulse width
+/// encodes the position of the stick, typically ranging from 1000 microseconds (full low) to 2000
+/// microseconds (full high).
+#[derive(Debug)]
+pub struct RcChannel {
+    pin: DigitalPin<Input>,
+    min_us: u16,
+    max_us: u16
+}
+
+impl RcChannel {
+    /// Create a new RC channel using the given pin.
+    ///
+    /// The pulse width range defaults to 1000 to 2000 microseconds.
+    pub fn new(pin: DigitalPin<Input>) -> RcChannel {
+        RcChannel {
+            pin: pin,
+            min_us: 1000,
+            max_us: 2000
+        }
+    }
+
+    /// Set the pulse widths in microseconds corresponding to the extreme positions.
+    ///
+    /// # Panics
+    /// Panics if `min_us` is not less than `max_us`.
+    #[inline]
+    pub fn range(mut self, min_us: u16, max_us: u16) -> RcChannel {
+        assert!(min_us < max_us, "invalid RC channel range");
+        self.min_us = min_us;
+        self.max_us = max_us;
+        self
+    }
+
+    /// Read the raw pulse width.
+    ///
+    /// Returns `None` if no pulse was received within 25 milliseconds, which typically means the
+    /// receiver has lost the transmitter.
+    #[inline]
+    pub fn read_pulse(&self) -> Option<Duration> {
+        self.pin.measure_pulse(PinLevel::High, Duration::from_millis(25))
+    }
+
+    /// Read the position of the channel between `-1000` and `1000`.
+    ///
+    /// The center of the configured range maps to `0`. Pulse widths outside of the range are
+    /// clamped. Returns `None` if no pulse was received in time.
+    pub fn read(&self) -> Option<i16> {
+        self.read_pulse().map(|width| {
+            let (min_us, max_us) = (self.min_us as u64, self.max_us as u64);
+            let width_us = width.as_micros().max(min_us).min(max_us);
+            let offset = (width_us - min_us) as i32 * 2000 / (max_us - min_us) as i32;
+            (offset - 1000) as i16
+        })
+    }
+
+    /// Release the pin used by this channel.
+    #[inline]
+    pub fn into_pin(self) -> DigitalPin<Input> {
+        self.pin
+    }
+}
@@ -0,0 +1,197 @@
+//! Pseudo-random number generation.
+//!
+//! Random number generators implement the `Rng` trait, which provides helpers for generating
+//! numbers in a range and filling buffers. Two generators are available: `ArduinoRng`, which uses
+//! the generator built into the Arduino library, and `XorShiftRng`, a small self-contained
+//! generator that can be used independently, e.g. one per task or interrupt service routine.
+//!
+//! Both generators are deterministic. To get a different sequence after every reset, seed them
+//! from physical noise using `entropy_seed` or `SeedableRng::from_entropy`.
+//!
+//! **Note**: None of these generators are suitable for cryptographic purposes.
+
+use ffi;
+use pins::AnalogInputPin;
+use time;
+
+use core::u32;
+
+/// A random number generator.
+pub trait Rng {
+    /// Generate a random `u32`.
+    fn next_u32(&mut self) -> u32;
+
+    /// Fill the given buffer with random bytes.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let value = self.next_u32();
+            for (i, byte) in chunk.iter_mut().enumerate() {
+                *byte = (value >> (8 * i)) as u8;
+            }
+        }
+    }
+
+    /// Generate a random number between `low` (inclusive) and `high` (exclusive).
+    ///
+    /// The numbers are uniformly distributed, unlike the commonly used `next_u32() % range`.
+    ///
+    /// # Panics
+    /// Panics if `low` is not less than `high`.
+    fn gen_range(&mut self, low: i32, high: i32) -> i32 {
+        assert!(low < high, "Rng::gen_range called with an empty range");
+
+        let range = high.wrapping_sub(low) as u32;
+        let zone = u32::MAX - u32::MAX % range;
+        loop {
+            let value = self.next_u32();
+            if value < zone {
+                return low.wrapping_add((value % range) as i32);
+            }
+        }
+    }
+
+    /// Generate a random boolean.
+    #[inline]
+    fn gen_bool(&mut self) -> bool {
+        self.next_u32() & 1 != 0
+    }
+}
+
+/// A random number generator that can be explicitly seeded.
+pub trait SeedableRng: Rng + Sized {
+    /// Create a new generator from the given seed.
+    fn from_seed(seed: u32) -> Self;
+
+    /// Reseed the generator, restarting its sequence.
+    fn reseed(&mut self, seed: u32);
+
+    /// Create a new generator seeded from physical noise.
+    ///
+    /// See `entropy_seed` for more information.
+    #[inline]
+    fn from_entropy(pin: &AnalogInputPin) -> Self {
+        Self::from_seed(entropy_seed(pin))
+    }
+}
+
+
+
+/// The random number generator built into the Arduino library.
+///
+/// All instances share the same global state, so reseeding one instance reseeds all of them. Note
+/// that the Arduino library ignores a seed of zero.
+///
+/// See also: [`random()`] and [`randomSeed()`] from the Arduino reference.
+///
+/// [`random()`]: https://www.arduino.cc/en/Reference/Random
+/// [`randomSeed()`]: https://www.arduino.cc/en/Reference/RandomSeed
+#[derive(Debug)]
+pub struct ArduinoRng {
+    _private: ()
+}
+
+impl ArduinoRng {
+    /// Obtain a handle to the Arduino random number generator without reseeding it.
+    ///
+    /// # Panics
+    /// Panics if this function is called from inside an interrupt service routine. Because the
+    /// Arduino library provides no guarantees with regards to reentrancy, this is required to avoid
+    /// an unsafe API.
+    #[inline]
+    pub fn new() -> ArduinoRng {
+        check_isr!("ArduinoRng::new");
+        ArduinoRng { _private: () }
+    }
+}
+
+impl Rng for ArduinoRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        let high = unsafe { ffi::rduino_random(0, 0x10000) } as u32;
+        let low = unsafe { ffi::rduino_random(0, 0x10000) } as u32;
+        high << 16 | low
+    }
+}
+
+impl SeedableRng for ArduinoRng {
+    #[inline]
+    fn from_seed(seed: u32) -> ArduinoRng {
+        let mut rng = ArduinoRng::new();
+        rng.reseed(seed);
+        rng
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u32) {
+        unsafe {
+            ffi::rduino_random_seed(seed);
+        }
+    }
+}
+
+impl !Send for ArduinoRng {}
+impl !Sync for ArduinoRng {}
+
+
+
+/// A small and fast xorshift random number generator.
+///
+/// Unlike `ArduinoRng`, every instance has its own state.
+#[derive(Clone, Debug)]
+pub struct XorShiftRng {
+    state: u32
+}
+
+impl Rng for XorShiftRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+}
+
+impl SeedableRng for XorShiftRng {
+    #[inline]
+    fn from_seed(seed: u32) -> XorShiftRng {
+        let mut rng = XorShiftRng { state: 0 };
+        rng.reseed(seed);
+        rng
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u32) {
+        // An all-zero state would only ever produce zeroes.
+        self.state = if seed != 0 { seed } else { 0x9e3779b9 };
+    }
+}
+
+
+
+/// Gather a seed from physical noise.
+///
+/// Reads the given analog input pin 32 times and mixes the readings with the jitter of the
+/// microsecond timer. For best results the pin should be left unconnected, so it picks up
+/// electrical noise.
+///
+/// **Note**: This function does not return a reliable result when interrupts are disabled.
+pub fn entropy_seed(pin: &AnalogInputPin) -> u32 {
+    let mut seed = 0u32;
+    for _ in 0..32 {
+        let start = time::microsecs();
+        let sample = pin.read() as u32;
+        let jitter = time::microsecs().wrapping_sub(start);
+
+        seed = (seed.rotate_left(7) ^ sample ^ jitter.rotate_left(16)).wrapping_mul(0x9e3779b1);
+    }
+
+    // Finalize so every bit of the seed depends on every reading.
+    seed ^= seed >> 16;
+    seed = seed.wrapping_mul(0x85ebca6b);
+    seed ^= seed >> 13;
+    seed = seed.wrapping_mul(0xc2b2ae35);
+    seed ^ seed >> 16
+}
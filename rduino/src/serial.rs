@@ -1,5 +1,10 @@
 use ffi::{self, RduinoSerial, RduinoSerialConfig};
+use interrupts;
 use io;
+use sys::statics::BoardLocal;
+use sys_common::serial::AsRawSerial;
+
+use core::mem;
 
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
 pub enum CharBits {
@@ -102,21 +107,40 @@ impl Default for SerialMode {
     }
 }
 
-#[derive(Clone, Debug)]
+/// An owned handle to a serial port.
+///
+/// Every port can only be open once at a time; the port is ended and can be opened again when the
+/// handle is dropped. Ports that share the same underlying hardware, such as `SerialPort::Monitor`
+/// and `SerialPort::Hardware` on most boards, count as the same port.
+///
+/// The `print!` and `println!` macros do not need a handle, they write to the monitor port
+/// directly. This works whether or not some `Serial` owns that port, but the port must have been
+/// started with `reset` first. To print to another port, pass its handle to `io::set_print`.
+#[derive(Debug)]
 pub struct Serial {
     inner: *mut RduinoSerial
 }
 
 impl Serial {
+    /// Open a serial port.
+    ///
+    /// # Errors
+    /// Returns `PortUnavailable` if the board does not have this port and `PortBusy` if it is
+    /// already open.
+    ///
+    /// # Panics
+    /// Panics when called from inside an interrupt service routine.
     #[inline]
-    fn new(inner: *mut RduinoSerial) -> Serial {
-        Serial { inner: inner }
-    }
-
-    #[inline]
-    pub fn open(port: SerialPort) -> Option<Serial> {
+    pub fn open(port: SerialPort) -> io::Result<Serial> {
         check_isr!("serial");
-        port.open()
+        let inner = port.to_ffi();
+        if inner.is_null() {
+            Err(io::Error::PortUnavailable)
+        } else if !claim_port(inner) {
+            Err(io::Error::PortBusy)
+        } else {
+            Ok(Serial { inner: inner })
+        }
     }
 
     #[inline]
@@ -144,24 +168,41 @@ impl Serial {
         while !self.ready() {}
     }
 
+    /// End the serial port and give it back, so it can be opened again.
+    ///
+    /// This is the same as dropping the handle. Inside an interrupt service routine the port is
+    /// only given back and keeps running, because ending it there is not safe.
     #[inline]
     pub fn close(self) {
-        unsafe {
-            ffi::rduino_serial_end(self.inner);
-        }
+        mem::drop(self);
     }
 }
 
-impl !Send for Serial {}
-impl !Sync for Serial {}
+impl Drop for Serial {
+    fn drop(&mut self) {
+        // Panicking in a destructor would abort, so a port dropped by an ISR is left running.
+        if !interrupts::inside_isr() {
+            unsafe {
+                ffi::rduino_serial_end(self.inner);
+            }
+        }
+        release_port(self.inner);
+    }
+}
 
-impl Default for Serial {
+impl AsRawSerial for Serial {
     #[inline]
-    fn default() -> Serial {
-        Serial { inner: unsafe { ffi::rduino_serial_default() } }
+    fn as_raw(&self) -> *mut RduinoSerial {
+        self.inner
     }
 }
 
+// The handle has exclusive access to its port, so it can be moved into an interrupt service routine
+// or used as a print target. Ending the port from within an ISR is not safe, which is why `drop`
+// only gives the port back there.
+unsafe impl Send for Serial {}
+impl !Sync for Serial {}
+
 impl io::Stream for Serial {
     #[inline]
     fn available(&self) -> usize {
@@ -232,7 +273,7 @@ pub enum SerialPort {
 }
 
 impl SerialPort {
-    fn open(&self) -> Option<Serial> {
+    fn to_ffi(&self) -> *mut RduinoSerial {
         let ffi_serial = match *self {
             SerialPort::UsbVirtual => ffi::rduino_serial_usbvirtual,
             SerialPort::Monitor => ffi::rduino_serial_monitor,
@@ -240,11 +281,73 @@ impl SerialPort {
             SerialPort::Hardware => ffi::rduino_serial_hardware,
             SerialPort::HardwareOpen => ffi::rduino_serial_hardware_open
         };
-        let serial = unsafe { ffi_serial() };
-        if serial.is_null() {
-            None
+        unsafe { ffi_serial() }
+    }
+}
+
+/// The number of `SerialPort` variants, which bounds the number of distinct open ports.
+const NUM_SERIAL_PORTS: usize = 5;
+
+static OPEN_PORTS: BoardLocal<[usize; NUM_SERIAL_PORTS]> =
+    BoardLocal::new([0; NUM_SERIAL_PORTS]);
+
+fn claim_port(inner: *mut RduinoSerial) -> bool {
+    OPEN_PORTS.with(|open_ports| {
+        if open_ports.contains(&(inner as usize)) {
+            return false;
+        }
+        // Every port is claimed at most once, so there is always a free entry left.
+        if let Some(entry) = open_ports.iter_mut().find(|entry| **entry == 0) {
+            *entry = inner as usize;
+        }
+        true
+    })
+}
+
+fn release_port(inner: *mut RduinoSerial) {
+    OPEN_PORTS.with(|open_ports| {
+        if let Some(entry) = open_ports.iter_mut().find(|entry| **entry == inner as usize) {
+            *entry = 0;
+        }
+    });
+}
+
+/// The serial monitor as used by `print!` and `println!`.
+///
+/// Unlike `Serial`, this does not own the port: it never starts or ends it and it can be used
+/// while the port is open elsewhere.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct __Monitor {
+    inner: *mut RduinoSerial
+}
+
+impl __Monitor {
+    #[inline]
+    pub fn new() -> __Monitor {
+        let mut inner = SerialPort::Monitor.to_ffi();
+        if inner.is_null() {
+            inner = unsafe { ffi::rduino_serial_default() };
+        }
+        __Monitor { inner: inner }
+    }
+}
+
+impl io::Write for __Monitor {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            Ok(0)
         } else {
-            Some(Serial::new(serial))
+            Ok(unsafe { ffi::rduino_serial_write_bytes(self.inner, buf.as_ptr(), buf.len()) })
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        unsafe {
+            ffi::rduino_serial_flush(self.inner);
         }
+        Ok(())
     }
 }
@@ -0,0 +1,249 @@
+//! Control over the simulated board.
+//!
+//! When the `host-sim` feature is enabled, the Arduino core and the C++ shim are replaced by a
+//! simulated board written in Rust, so firmware logic can be exercised with `cargo test` on the
+//! development machine. The functions in this module play the part of the outside world: they
+//! drive input pins, inspect outputs, advance the clock, exchange serial data and trigger
+//! interrupts.
+//!
+//! The simulated board is loosely modelled after the Arduino Uno: it has 20 digital pins, of which
+//! pins 14 to 19 are the analog inputs `A0` to `A5`, PWM on pins 3, 5, 6, 9, 10 and 11 and external
+//! interrupts 0 and 1 on pins 2 and 3. Like on the Uno, every pin can also be used as a pin change
+//! interrupt through `platform::host::pins::DigitalPinExt`. `SerialPort::Monitor` and
+//! `SerialPort::Hardware` refer to the default serial port and `SerialPort::HardwareOpen` to a
+//! second one. The timers `Timer1` and `Timer2` from `platform::host` generate periodic interrupts
+//! as the clock advances and there is 1 KiB of EEPROM.
+//!
+//! Every thread has its own board, including its own `Pins` singleton, clock, serial ports and
+//! interrupts. All other state of the crate, such as the other singletons, panic hooks and
+//! statics declared by the firmware, is shared between threads. Tests running in parallel must not
+//! rely on such state, or run one at a time. Time only passes when it is advanced explicitly or
+//! when the firmware sleeps.
+//!
+//! This module is only available with the `host-sim` feature.
+
+use ffi::{RduinoPinLevel, RduinoPinMode};
+use pins::{PinLevel, PinMode};
+use serial::SerialPort;
+use sys::board;
+
+use std::vec::Vec;
+
+/// A tone generated on a pin.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Tone {
+    pin: u8,
+    frequency: u32
+}
+
+impl Tone {
+    /// The number of the digital pin generating the tone.
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// The frequency of the tone in hertz.
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+}
+
+/// Restore the board to its power-on state.
+///
+/// Resets all pins, the clock, the serial ports and detaches all interrupt service routines. The
+/// pins can be taken again with `Pins::take` and the serial ports opened again with `Serial::open`
+/// afterwards.
+///
+/// Like switching the power off and on again, this keeps the contents of the EEPROM, but not the
+/// last panic stored in RAM.
+pub fn reset() {
+    board::reset();
+}
+
+/// Reset the board from software, like the watchdog timer does.
+///
+/// Unlike `reset`, this also keeps the last panic stored in RAM.
+pub fn restart() {
+    board::restart();
+}
+
+/// Apply a level to a digital pin from the outside.
+///
+/// Triggers the pin's interrupt service routine if one is attached and the level change matches
+/// its mode. While the pin is an output, the level models a load that overpowers the driver: it is
+/// read from the pin, but the level the pin is set to does not change.
+///
+/// # Panics
+/// Panics if the pin does not exist.
+pub fn set_input(pin: u8, level: PinLevel) {
+    let level = match level {
+        PinLevel::Low => RduinoPinLevel::Low,
+        PinLevel::High => RduinoPinLevel::High
+    };
+    board::update_pin(pin, |state| state.input = Some(level));
+}
+
+/// Stop applying a level to a digital pin, leaving it floating.
+///
+/// # Panics
+/// Panics if the pin does not exist.
+pub fn release_input(pin: u8) {
+    board::update_pin(pin, |state| state.input = None);
+}
+
+/// The level the firmware currently reads from or drives on a digital pin.
+///
+/// # Panics
+/// Panics if the pin does not exist.
+pub fn level(pin: u8) -> PinLevel {
+    match board::with_board(|board| board.pins[pin as usize].level()) {
+        RduinoPinLevel::Low => PinLevel::Low,
+        RduinoPinLevel::High => PinLevel::High
+    }
+}
+
+/// The mode of a pin, or `None` if the firmware never set it.
+///
+/// # Panics
+/// Panics if the pin does not exist.
+pub fn mode(pin: u8) -> Option<PinMode> {
+    board::with_board(|board| board.pins[pin as usize].mode).map(|mode| {
+        match mode {
+            RduinoPinMode::Input => PinMode::Input,
+            RduinoPinMode::InputPullup => PinMode::InputPullup,
+            RduinoPinMode::Output => PinMode::Output,
+            mode => PinMode::__Raw(mode)
+        }
+    })
+}
+
+/// Apply an analog value to an analog input pin.
+///
+/// The pin is identified by its *digital* number, as returned by `Pin::number`. The value read by
+/// the firmware is clamped to the current read resolution.
+///
+/// # Panics
+/// Panics if the pin does not exist.
+pub fn set_analog_input(pin: u8, value: u16) {
+    board::with_board(|board| board.pins[pin as usize].analog_input = value);
+}
+
+/// The analog value last written to a pin, or `None` if nothing was written.
+///
+/// # Panics
+/// Panics if the pin does not exist.
+pub fn analog_output(pin: u8) -> Option<u16> {
+    board::with_board(|board| board.pins[pin as usize].analog_output)
+}
+
+/// The tone currently being generated, if any.
+pub fn tone() -> Option<Tone> {
+    board::with_board(|board| board.active_tone()).map(|tone| {
+        Tone { pin: tone.pin, frequency: tone.frequency }
+    })
+}
+
+/// Take all bytes shifted out on the given data pin so far.
+///
+/// The bytes are returned as they would end up in an MSB-first shift register such as the 74HC595,
+/// regardless of the bit order used by the firmware.
+pub fn take_shifted_out(data_pin: u8) -> Vec<u8> {
+    board::with_board(|board| {
+        let (taken, kept): (Vec<_>, Vec<_>) = board.shifted_out.drain(..)
+                                                     .partition(|&(pin, _)| pin == data_pin);
+        board.shifted_out = kept;
+        taken.into_iter().map(|(_, value)| value).collect()
+    })
+}
+
+/// Load bytes into the chain of shift registers read on the given data pin.
+///
+/// The bytes are given as they would be loaded into an MSB-first shift register such as the
+/// 74HC165, replacing any bits that were not shifted out yet. The registers shift on every rising
+/// edge of the clock, and shifting in past the loaded bytes yields zeros.
+pub fn push_shift_in(data_pin: u8, bytes: &[u8]) {
+    board::with_board(|board| {
+        board.shift_in.retain(|&(pin, _)| pin != data_pin);
+        for &value in bytes {
+            board.shift_in.extend((0..8).map(|bit| (data_pin, value & (0x80 >> bit) != 0)));
+        }
+    });
+}
+
+/// Queue a pulse on a pin, to be measured by the firmware.
+///
+/// Measuring a pulse consumes the first queued pulse with a matching pin and level and advances
+/// the clock by its width. If no such pulse is queued, or its width exceeds the timeout, the clock
+/// advances by the timeout instead.
+pub fn push_pulse(pin: u8, level: PinLevel, width_us: u32) {
+    let level = match level {
+        PinLevel::Low => RduinoPinLevel::Low,
+        PinLevel::High => RduinoPinLevel::High
+    };
+    board::with_board(|board| board.pulses.push_back((pin, level, width_us)));
+}
+
+/// The time since the board was reset in microseconds.
+///
+/// Unlike `time::microsecs`, this clock does not wrap around.
+pub fn micros() -> u64 {
+    board::with_board(|board| board.micros)
+}
+
+/// Advance the clock by the given number of microseconds.
+///
+/// Interrupt service routines attached to a timer run for every period that passes.
+pub fn advance_micros(us: u64) {
+    board::advance(us);
+}
+
+/// Advance the clock by the given number of milliseconds.
+pub fn advance_millis(ms: u64) {
+    advance_micros(ms * 1000);
+}
+
+/// Send bytes to the firmware over a serial port.
+///
+/// # Panics
+/// Panics if the serial port does not exist on the simulated board.
+pub fn push_serial_input(port: SerialPort, bytes: &[u8]) {
+    let port = serial_port(port);
+    board::with_board(|board| board.serials[port].input.extend(bytes.iter().cloned()));
+}
+
+/// Take all bytes written by the firmware to a serial port so far.
+///
+/// # Panics
+/// Panics if the serial port does not exist on the simulated board.
+pub fn take_serial_output(port: SerialPort) -> Vec<u8> {
+    let port = serial_port(port);
+    board::with_board(|board| board.serials[port].output.split_off(0))
+}
+
+/// The speed a serial port was started with, or `None` if it is not started.
+///
+/// # Panics
+/// Panics if the serial port does not exist on the simulated board.
+pub fn serial_speed(port: SerialPort) -> Option<u32> {
+    let port = serial_port(port);
+    board::with_board(|board| board.serials[port].speed)
+}
+
+fn serial_port(port: SerialPort) -> usize {
+    match port {
+        SerialPort::Monitor | SerialPort::Hardware => 0,
+        SerialPort::HardwareOpen => 1,
+        port => panic!("serial port {:?} does not exist on the simulated board", port)
+    }
+}
+
+/// Trigger an external interrupt.
+///
+/// The attached interrupt service routine runs with interrupts disabled, just like on real
+/// hardware. If interrupts are currently disabled, the routine runs as soon as they are enabled
+/// again.
+///
+/// Returns whether an interrupt service routine was attached to the interrupt.
+pub fn trigger_interrupt(interrupt: u8) -> bool {
+    board::trigger_interrupt(interrupt)
+}
@@ -1,10 +1,16 @@
 //! Synchronization primitives.
+//!
+//! A `Mutex` protects a resource by disabling interrupts while it is locked. For passing values
+//! from interrupt service routines to the main program a `Queue` is usually a better fit, since it
+//! never disables interrupts. Simple values such as counters can be shared through an
+//! `AtomicCell`.
 
+use sys::statics::QueueIndex;
+use sys_common::atomic;
 use sys_common::sync::CriticalSection;
 
 use core::cell::UnsafeCell;
-use core::fmt;
-use core::ops;
+use core::{fmt, mem, ops, ptr};
 
 /// A synchronization primitive for gaining exclusive access to some resource.
 ///
@@ -88,3 +94,339 @@ impl<'mutex, T: ?Sized + 'mutex> ops::DerefMut for MutexGuard<'mutex, T> {
         unsafe { &mut *self.data.get() }
     }
 }
+
+
+
+/// A memory location of a `Copy` type that can be shared between interrupt service routines and
+/// the main program.
+///
+/// Values with the size of an atomic integer that is natively supported by the target, such as
+/// `u32` on ARM, are accessed with atomic instructions. Other values, and all values on AVR, where
+/// only some of `core::sync::atomic` is available, are accessed with interrupts disabled for the
+/// duration of a single operation.
+pub struct AtomicCell<T> {
+    value: UnsafeCell<T>
+}
+
+impl<T> AtomicCell<T> {
+    /// Create a new cell holding the given initial value.
+    pub const fn new(value: T) -> AtomicCell<T> {
+        AtomicCell { value: UnsafeCell::new(value) }
+    }
+
+    /// Get a mutable reference to the value.
+    ///
+    /// No synchronization is needed, since the mutable borrow guarantees exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.value.get() }
+    }
+
+    /// Consume the cell and return the value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Copy> AtomicCell<T> {
+    /// Load the value.
+    #[inline]
+    pub fn load(&self) -> T {
+        unsafe { atomic::load(self.value.get()) }
+    }
+
+    /// Store a value.
+    #[inline]
+    pub fn store(&self, value: T) {
+        unsafe { atomic::store(self.value.get(), value) }
+    }
+
+    /// Store a value and return the previous one.
+    #[inline]
+    pub fn swap(&self, value: T) -> T {
+        unsafe { atomic::swap(self.value.get(), value) }
+    }
+
+    /// Replace the value with the result of a function.
+    ///
+    /// Returns `Ok` with the previous value if the function returned a new value, or `Err` with
+    /// the current value if it returned `None`. The function may be called more than once if the
+    /// value is changed by an interrupt service routine in the meantime, so it should not have
+    /// side effects.
+    #[inline]
+    pub fn fetch_update<F: FnMut(T) -> Option<T>>(&self, f: F) -> Result<T, T> {
+        unsafe { atomic::update(self.value.get(), f) }
+    }
+}
+
+impl<T: Copy + PartialEq> AtomicCell<T> {
+    /// Store a new value if the current value equals `current`.
+    ///
+    /// Returns `Ok` with the previous value if the value was replaced, otherwise `Err` with the
+    /// current value.
+    #[inline]
+    pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T> {
+        self.fetch_update(|value| if value == current { Some(new) } else { None })
+    }
+}
+
+macro_rules! atomic_cell_integer_impls {
+    ($($int:ident)*) => {
+        $(
+            impl AtomicCell<$int> {
+                /// Add to the value, wrapping around on overflow, and return the previous value.
+                #[inline]
+                pub fn fetch_add(&self, value: $int) -> $int {
+                    self.fetch_update(|old| Some(old.wrapping_add(value))).unwrap_or_else(|old| old)
+                }
+
+                /// Subtract from the value, wrapping around on overflow, and return the previous
+                /// value.
+                #[inline]
+                pub fn fetch_sub(&self, value: $int) -> $int {
+                    self.fetch_update(|old| Some(old.wrapping_sub(value))).unwrap_or_else(|old| old)
+                }
+            }
+        )*
+    }
+}
+
+atomic_cell_integer_impls!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize);
+
+unsafe impl<T: Copy + Send> Sync for AtomicCell<T> {}
+
+impl<T: Default> Default for AtomicCell<T> {
+    #[inline]
+    fn default() -> AtomicCell<T> {
+        AtomicCell::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for AtomicCell<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "AtomicCell {{ value: {:?} }}", self.load())
+    }
+}
+
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A fixed-size array that can be used as the buffer of a `Queue`.
+///
+/// This trait is implemented for arrays of `Copy` elements with 2 to 16, 32, 64, 128 or 256
+/// elements. It is sealed: the queue stores its indices in a single byte and relies on `LEN` being
+/// the actual length of the array, so it can not be implemented outside of this crate.
+pub trait Array: sealed::Sealed {
+    /// The type of the elements.
+    type Item: Copy;
+
+    /// The number of elements, at most 256.
+    const LEN: usize;
+}
+
+macro_rules! array_impls {
+    ($($len:tt)*) => {
+        $(
+            impl<T: Copy> sealed::Sealed for [T; $len] {}
+
+            impl<T: Copy> Array for [T; $len] {
+                type Item = T;
+                const LEN: usize = $len;
+            }
+        )*
+    }
+}
+
+array_impls!(2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 32 64 128 256);
+
+/// A fixed-capacity queue with a single producer and a single consumer.
+///
+/// The queue is split into a `Producer` and a `Consumer`, which can be used from different
+/// contexts, typically an interrupt service routine and the main program. Neither half ever
+/// disables interrupts: each index of the ring buffer is only written by one of the halves and
+/// read and written as a single byte.
+///
+/// The queue is backed by an array that is given to `new`, so it can be created in a `static`.
+/// One element of the array is kept free to tell a full queue from an empty one, so the capacity
+/// is one less than the length of the array.
+pub struct Queue<A> {
+    buffer: UnsafeCell<A>,
+    // The index of the next element to dequeue, only written by the consumer.
+    head: QueueIndex,
+    // The index of the next element to enqueue, only written by the producer.
+    tail: QueueIndex,
+    split: Mutex<bool>
+}
+
+impl<A> Queue<A> {
+    /// Create an empty queue backed by the given array.
+    ///
+    /// The initial contents of the array are never read.
+    pub const fn new(buffer: A) -> Queue<A> {
+        Queue {
+            buffer: UnsafeCell::new(buffer),
+            head: QueueIndex::new(),
+            tail: QueueIndex::new(),
+            split: Mutex::new(false)
+        }
+    }
+}
+
+impl<A: Array> Queue<A> {
+    /// Split the queue into its producer and consumer halves.
+    ///
+    /// Returns `None` if the queue was already split, so there is never more than one producer and
+    /// one consumer.
+    pub fn split(&self) -> Option<(Producer<A>, Consumer<A>)> {
+        if mem::replace(&mut *self.split.lock(), true) {
+            None
+        } else {
+            Some((Producer { queue: self }, Consumer { queue: self }))
+        }
+    }
+
+    /// The maximum number of elements in the queue.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        A::LEN - 1
+    }
+
+    /// The number of elements in the queue.
+    ///
+    /// The result may be outdated as soon as it is returned, if the other half of the queue is in
+    /// use by an interrupt service routine.
+    #[inline]
+    pub fn len(&self) -> usize {
+        let (head, tail) = (self.head() as usize, self.tail() as usize);
+        if tail >= head {
+            tail - head
+        } else {
+            A::LEN - head + tail
+        }
+    }
+
+    /// Whether the queue is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.head() == self.tail()
+    }
+
+    /// Whether the queue is full.
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.next(self.tail()) == self.head()
+    }
+
+    #[inline]
+    fn head(&self) -> u8 {
+        self.head.acquire()
+    }
+
+    #[inline]
+    fn tail(&self) -> u8 {
+        self.tail.acquire()
+    }
+
+    #[inline]
+    fn next(&self, index: u8) -> u8 {
+        if index as usize + 1 == A::LEN {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    #[inline]
+    fn slot(&self, index: u8) -> *mut A::Item {
+        unsafe { (self.buffer.get() as *mut A::Item).offset(index as isize) }
+    }
+}
+
+unsafe impl<A: Array> Sync for Queue<A> where A::Item: Send {}
+
+impl<A: Array> fmt::Debug for Queue<A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Queue {{ len: {}, capacity: {} }}", self.len(), self.capacity())
+    }
+}
+
+/// The half of a `Queue` that adds elements.
+pub struct Producer<'a, A: Array + 'a> {
+    queue: &'a Queue<A>
+}
+
+impl<'a, A: Array + 'a> Producer<'a, A> {
+    /// Add an element to the back of the queue.
+    ///
+    /// Returns the element back if the queue is full.
+    pub fn enqueue(&mut self, item: A::Item) -> Result<(), A::Item> {
+        let tail = self.queue.tail();
+        let next = self.queue.next(tail);
+        if next == self.queue.head() {
+            return Err(item);
+        }
+
+        unsafe {
+            ptr::write(self.queue.slot(tail), item);
+        }
+        // The element is written before the consumer can see it.
+        self.queue.tail.release(next);
+        Ok(())
+    }
+
+    /// Whether the queue is full.
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.queue.is_full()
+    }
+}
+
+impl<'a, A: Array + 'a> fmt::Debug for Producer<'a, A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Producer").field("queue", self.queue).finish()
+    }
+}
+
+/// The half of a `Queue` that removes elements.
+pub struct Consumer<'a, A: Array + 'a> {
+    queue: &'a Queue<A>
+}
+
+impl<'a, A: Array + 'a> Consumer<'a, A> {
+    /// Remove the element at the front of the queue.
+    ///
+    /// Returns `None` if the queue is empty.
+    pub fn dequeue(&mut self) -> Option<A::Item> {
+        let head = self.queue.head();
+        if head == self.queue.tail() {
+            return None;
+        }
+
+        // The element is read after the producer published it and before the producer can
+        // overwrite it.
+        let item = unsafe { ptr::read(self.queue.slot(head)) };
+        self.queue.head.release(self.queue.next(head));
+        Some(item)
+    }
+
+    /// The number of elements in the queue.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Whether the queue is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl<'a, A: Array + 'a> fmt::Debug for Consumer<'a, A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Consumer").field("queue", self.queue).finish()
+    }
+}
@@ -1,5 +1,11 @@
+use ffi::RduinoTimer;
+
 pub type Register = u32;
 
+/// The timer that `tone()` reprograms. Cores that generate tones with TC3 or TC4 already clash with
+/// the handlers of the `samd-timers` feature when linking, so there is none.
+pub const TONE_TIMER: Option<RduinoTimer> = None;
+
 #[inline]
 pub unsafe fn disable_interrupts() -> Register {
     let primask: u32;
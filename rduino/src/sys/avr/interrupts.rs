@@ -1,7 +1,11 @@
-use core::intrinsics;
+use ffi::RduinoTimer;
+use sys_common::interrupts::IsrDepth;
 
 pub type Register = u8;
 
+/// The timer that `tone()` reprograms, where it exists.
+pub const TONE_TIMER: Option<RduinoTimer> = Some(RduinoTimer::Timer2);
+
 #[inline]
 pub unsafe fn disable_interrupts() -> Register {
     let sreg: u8;
@@ -14,28 +18,21 @@ pub unsafe fn restore_interrupts(prev_sreq: Register) {
     asm!("out sreg, $0" :: "r"(prev_sreq) :: "volatile");
 }
 
-// FIXME: does not work for nested interrupts
-static mut INSIDE_ISR: bool = false;
+static ISR_DEPTH: IsrDepth = IsrDepth::new();
 
 #[inline]
 pub fn inside_isr() -> bool {
-    unsafe { INSIDE_ISR }
+    ISR_DEPTH.get() > 0
 }
 
 #[inline]
 #[doc(hidden)]
 pub fn __enter_isr() {
-    unsafe {
-        INSIDE_ISR = true;
-        intrinsics::atomic_singlethreadfence();
-    }
+    ISR_DEPTH.enter();
 }
 
 #[inline]
 #[doc(hidden)]
 pub fn __exit_isr() {
-    unsafe {
-        INSIDE_ISR = false;
-        intrinsics::atomic_singlethreadfence();
-    }
+    ISR_DEPTH.exit();
 }
@@ -0,0 +1,428 @@
+//! State of the simulated board.
+//!
+//! The board is loosely modelled after the Arduino Uno: 20 digital pins of which `A0` to `A5` are
+//! pins 14 to 19, PWM on pins 3, 5, 6, 9, 10 and 11 and external interrupts 0 and 1 on pins 2 and
+//! 3, while every pin has a pin change interrupt. In addition to the default serial port it has a
+//! second, open hardware serial port. Like the Uno it runs at 16 MHz, has the periodic timer
+//! interrupts `Timer1` and `Timer2` and 1 KiB of EEPROM.
+//!
+//! Every thread owns its own board, including the state the crate keeps in `BoardLocal` statics,
+//! see `sys::statics`. The other statics of the crate are still shared between threads, so they
+//! are protected by a global lock that is held while interrupts are disabled, see
+//! `sys::interrupts`.
+
+use ffi::{RduinoAnalogReference, RduinoBitOrder, RduinoInterruptMode, RduinoPinLevel, RduinoPinMode,
+          RduinoTimer};
+use sys::interrupts;
+use sys_common::sync::CriticalSection;
+
+use std::any::Any;
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::iter;
+use std::mem;
+use std::vec::Vec;
+
+pub const NUM_DIGITAL_PINS: usize = 20;
+pub const NUM_INTERRUPTS: usize = 2;
+pub const PIN_CHANGE_INTERRUPT_BASE: usize = 128;
+pub const NUM_SERIAL_PORTS: usize = 2;
+pub const NUM_TIMERS: usize = 2;
+pub const CYCLES_PER_MICRO: u64 = 16;
+pub const SERIAL_BUFFER_SIZE: usize = 64;
+pub const EEPROM_SIZE: usize = 1024;
+
+pub const ANALOG_INPUT_PINS: [u8; 6] = [14, 15, 16, 17, 18, 19];
+pub const PWM_PINS: [u8; 6] = [3, 5, 6, 9, 10, 11];
+
+#[derive(Clone, Copy, Debug)]
+pub struct PinState {
+    pub mode: Option<RduinoPinMode>,
+    pub output: RduinoPinLevel,
+    pub input: Option<RduinoPinLevel>,
+    pub analog_input: u16,
+    pub analog_output: Option<u16>
+}
+
+impl PinState {
+    fn new() -> PinState {
+        PinState {
+            mode: None,
+            output: RduinoPinLevel::Low,
+            input: None,
+            analog_input: 0,
+            analog_output: None
+        }
+    }
+
+    /// The level observed when reading the pin.
+    ///
+    /// Output pins read back their own output, unless a level is applied externally, which models
+    /// a load that overpowers the driver. Input pins read the externally applied level or fall back
+    /// to their pull resistor. Floating pins read low.
+    pub fn level(&self) -> RduinoPinLevel {
+        match self.mode {
+            Some(RduinoPinMode::Output) => self.input.unwrap_or(self.output),
+            Some(RduinoPinMode::InputPullup) => self.input.unwrap_or(RduinoPinLevel::High),
+            _ => self.input.unwrap_or(RduinoPinLevel::Low)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Isr {
+    pub routine: unsafe extern "C" fn(),
+    pub mode: RduinoInterruptMode
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimerState {
+    pub routine: unsafe extern "C" fn(),
+    /// The number of clock cycles between two interrupts.
+    pub period: u64,
+    /// The clock cycle at which the next interrupt occurs.
+    pub next: u64,
+    pub pending: bool
+}
+
+#[derive(Debug)]
+pub struct SerialState {
+    pub speed: Option<u32>,
+    pub timeout: u32,
+    pub input: VecDeque<u8>,
+    pub output: Vec<u8>
+}
+
+impl SerialState {
+    fn new() -> SerialState {
+        SerialState {
+            speed: None,
+            timeout: 1000,
+            input: VecDeque::new(),
+            output: Vec::new()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ToneState {
+    pub pin: u8,
+    pub frequency: u32,
+    pub end: Option<u64>
+}
+
+#[derive(Debug)]
+pub struct Board {
+    pub pins: [PinState; NUM_DIGITAL_PINS],
+    pub micros: u64,
+    pub analog_reference: RduinoAnalogReference,
+    pub analog_read_resolution: u8,
+    pub analog_write_resolution: u8,
+    /// The ISRs of the external interrupts, followed by those of the pin change interrupts.
+    pub isrs: [Option<Isr>; NUM_INTERRUPTS + NUM_DIGITAL_PINS],
+    pub pending: [bool; NUM_INTERRUPTS + NUM_DIGITAL_PINS],
+    pub timers: [Option<TimerState>; NUM_TIMERS],
+    pub serials: [SerialState; NUM_SERIAL_PORTS],
+    pub tone: Option<ToneState>,
+    pub shifted_out: Vec<(u8, u8)>,
+    /// The bits loaded into the shift registers read on each data pin, starting with the one on
+    /// the data pin.
+    pub shift_in: VecDeque<(u8, bool)>,
+    pub pulses: VecDeque<(u8, RduinoPinLevel, u32)>,
+    pub random_seed: u32,
+    pub eeprom: Vec<u8>,
+    /// The values of the `BoardLocal` statics of the crate, by their address. Values that are kept
+    /// on a software reset are marked.
+    pub statics: HashMap<usize, (bool, Box<Any>)>
+}
+
+impl Board {
+    fn new() -> Board {
+        Board {
+            pins: [PinState::new(); NUM_DIGITAL_PINS],
+            micros: 0,
+            analog_reference: RduinoAnalogReference::Default,
+            analog_read_resolution: 10,
+            analog_write_resolution: 8,
+            isrs: [None; NUM_INTERRUPTS + NUM_DIGITAL_PINS],
+            pending: [false; NUM_INTERRUPTS + NUM_DIGITAL_PINS],
+            timers: [None; NUM_TIMERS],
+            serials: [SerialState::new(), SerialState::new()],
+            tone: None,
+            shifted_out: Vec::new(),
+            shift_in: VecDeque::new(),
+            pulses: VecDeque::new(),
+            random_seed: 1,
+            eeprom: iter::repeat(0xff).take(EEPROM_SIZE).collect(),
+            statics: HashMap::new()
+        }
+    }
+
+    /// The currently sounding tone, if any.
+    pub fn active_tone(&self) -> Option<ToneState> {
+        match self.tone {
+            Some(ToneState { end: Some(end), .. }) if end <= self.micros => None,
+            tone => tone
+        }
+    }
+
+    /// Draw a number from the same "minimal standard" generator used by avr-libc's `random()`.
+    pub fn random(&mut self, min: i32, max: i32) -> i32 {
+        if min >= max {
+            return min;
+        }
+
+        let mut x = self.random_seed as i64;
+        if x == 0 {
+            x = 123459876;
+        }
+        let hi = x / 127773;
+        let lo = x % 127773;
+        x = 16807 * lo - 2836 * hi;
+        if x < 0 {
+            x += 0x7fffffff;
+        }
+        self.random_seed = x as u32;
+
+        let diff = max as i64 - min as i64;
+        (min as i64 + x % diff) as i32
+    }
+}
+
+thread_local! {
+    static BOARD: RefCell<Board> = RefCell::new(Board::new());
+}
+
+/// Run a closure with exclusive access to the board of the current thread.
+///
+/// **Note**: The closure must not call back into the simulation, interrupt service routines in
+/// particular must only be run after it returns.
+pub fn with_board<T, F: FnOnce(&mut Board) -> T>(f: F) -> T {
+    BOARD.with(|board| f(&mut board.borrow_mut()))
+}
+
+/// Restore the board of the current thread to its power-on state.
+///
+/// Only the contents of the EEPROM survive.
+pub fn reset() {
+    with_board(|board| {
+        let eeprom = mem::replace(&mut board.eeprom, Vec::new());
+        *board = Board::new();
+        board.eeprom = eeprom;
+    });
+}
+
+/// Reset the board of the current thread from software, keeping the EEPROM and the statics that
+/// are kept on a reset.
+pub fn restart() {
+    with_board(|board| {
+        let eeprom = mem::replace(&mut board.eeprom, Vec::new());
+        let mut statics = mem::replace(&mut board.statics, HashMap::new());
+        statics.retain(|_, &mut (noinit, _)| noinit);
+        *board = Board::new();
+        board.eeprom = eeprom;
+        board.statics = statics;
+    });
+}
+
+pub fn interrupt_for_pin(pin: u8) -> Option<u8> {
+    match pin {
+        2 => Some(0),
+        3 => Some(1),
+        _ => None
+    }
+}
+
+/// The index of an external or pin change interrupt in `Board::isrs`.
+pub fn isr_index(interrupt: u8) -> Option<usize> {
+    let interrupt = interrupt as usize;
+    if interrupt < NUM_INTERRUPTS {
+        Some(interrupt)
+    } else if interrupt >= PIN_CHANGE_INTERRUPT_BASE
+              && interrupt - PIN_CHANGE_INTERRUPT_BASE < NUM_DIGITAL_PINS {
+        Some(NUM_INTERRUPTS + interrupt - PIN_CHANGE_INTERRUPT_BASE)
+    } else {
+        None
+    }
+}
+
+/// Update the state of a pin, triggering its interrupts if the level change matches.
+pub fn update_pin<F: FnOnce(&mut PinState)>(pin: u8, f: F) {
+    let triggered = with_board(|board| {
+        let (before, after) = {
+            let state = &mut board.pins[pin as usize];
+            let before = state.level();
+            f(state);
+            (before, state.level())
+        };
+
+        let pin_change = (PIN_CHANGE_INTERRUPT_BASE + pin as usize) as u8;
+        let interrupts = [interrupt_for_pin(pin), Some(pin_change)];
+        let mut triggered = [None, None];
+        for (slot, interrupt) in triggered.iter_mut().zip(&interrupts) {
+            *slot = interrupt.and_then(|interrupt| {
+                match board.isrs[isr_index(interrupt).unwrap()] {
+                    Some(isr) if is_triggered(isr.mode, before, after) => Some(interrupt),
+                    _ => None
+                }
+            });
+        }
+        triggered
+    });
+
+    for interrupt in triggered.iter().filter_map(|&interrupt| interrupt) {
+        trigger_interrupt(interrupt);
+    }
+}
+
+fn is_triggered(mode: RduinoInterruptMode, before: RduinoPinLevel, after: RduinoPinLevel) -> bool {
+    if before == after {
+        return false;
+    }
+
+    match mode {
+        RduinoInterruptMode::Change => true,
+        RduinoInterruptMode::Low | RduinoInterruptMode::Falling => after == RduinoPinLevel::Low,
+        RduinoInterruptMode::High | RduinoInterruptMode::Rising => after == RduinoPinLevel::High
+    }
+}
+
+/// Trigger an external or pin change interrupt.
+///
+/// Runs the attached ISR immediately or, if interrupts are disabled, as soon as they are enabled
+/// again. Returns whether an ISR was attached.
+pub fn trigger_interrupt(interrupt: u8) -> bool {
+    let index = match isr_index(interrupt) {
+        Some(index) => index,
+        None => return false
+    };
+    let isr = match with_board(|board| board.isrs[index]) {
+        Some(isr) => isr,
+        None => return false
+    };
+
+    if interrupts::interrupts_enabled() {
+        run_isr(isr.routine);
+    } else {
+        with_board(|board| board.pending[index] = true);
+    }
+    true
+}
+
+/// The number of clock cycles between the interrupts of a timer running at the given frequency.
+///
+/// The period is rounded the same way the Uno's timers round it. Returns `None` if the timer does
+/// not exist or can not produce the frequency.
+pub fn timer_period(timer: RduinoTimer, frequency: u32) -> Option<u64> {
+    let (prescalers, max_top): (&[u64], u64) = match timer {
+        RduinoTimer::Timer1 => (&[1, 8, 64, 256, 1024], 0x10000),
+        RduinoTimer::Timer2 => (&[1, 8, 32, 64, 128, 256, 1024], 0x100),
+        _ => return None
+    };
+    if frequency == 0 {
+        return None;
+    }
+
+    let cpu_frequency = CYCLES_PER_MICRO * 1_000_000;
+    for &prescaler in prescalers {
+        let top = cpu_frequency / (prescaler * frequency as u64);
+        if top == 0 {
+            return None;
+        }
+        if top <= max_top {
+            return Some(top * prescaler);
+        }
+    }
+    None
+}
+
+/// Advance the clock, running the ISRs of the timers that expire on the way.
+pub fn advance(us: u64) {
+    let end = with_board(|board| board.micros + us);
+    loop {
+        let timer = with_board(|board| {
+            let next = board.timers.iter().enumerate().filter_map(|(index, timer)| {
+                timer.map(|timer| (index, timer.next))
+            }).min_by_key(|&(_, next)| next);
+
+            match next {
+                Some((index, next)) if next <= end * CYCLES_PER_MICRO => {
+                    board.micros = board.micros.max(next / CYCLES_PER_MICRO);
+                    let timer = board.timers[index].as_mut().unwrap();
+                    timer.next += timer.period;
+                    Some(index)
+                }
+                _ => {
+                    board.micros = board.micros.max(end);
+                    None
+                }
+            }
+        });
+
+        match timer {
+            Some(timer) => trigger_timer(timer),
+            None => break
+        }
+    }
+}
+
+fn trigger_timer(timer: usize) {
+    let routine = with_board(|board| board.timers[timer].map(|timer| timer.routine));
+    if let Some(routine) = routine {
+        if interrupts::interrupts_enabled() {
+            run_isr(routine);
+        } else {
+            with_board(|board| board.timers[timer].as_mut().unwrap().pending = true);
+        }
+    }
+}
+
+/// Run all interrupts that were triggered while interrupts were disabled.
+pub fn run_pending_interrupts() {
+    loop {
+        let routine = with_board(|board| {
+            if let Some(interrupt) = board.pending.iter().position(|&pending| pending) {
+                board.pending[interrupt] = false;
+                return board.isrs[interrupt].map(|isr| isr.routine);
+            }
+
+            board.timers.iter_mut().filter_map(Option::as_mut).find(|timer| timer.pending)
+                 .map(|timer| {
+                     timer.pending = false;
+                     timer.routine
+                 })
+        });
+
+        match routine {
+            Some(routine) => run_isr(routine),
+            None => break
+        }
+    }
+}
+
+/// Run a routine the way the hardware runs an ISR: with interrupts disabled.
+pub fn run_isr(routine: unsafe extern "C" fn()) {
+    // The critical section also ends when the routine panics, so other threads are not locked out.
+    let _lock = CriticalSection::enter();
+    unsafe {
+        routine();
+    }
+}
+
+/// Translate a byte shifted in the given bit order to the contents of an MSB-first shift register
+/// such as the 74HC595, and vice versa.
+pub fn bit_order_value(bit_order: RduinoBitOrder, value: u8) -> u8 {
+    match bit_order {
+        RduinoBitOrder::MsbFirst => value,
+        RduinoBitOrder::LsbFirst => {
+            let mut reversed = 0;
+            for bit in 0..8 {
+                if value & (1 << bit) != 0 {
+                    reversed |= 0x80 >> bit;
+                }
+            }
+            reversed
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use ffi::RduinoTimer;
+use sys::board;
+use sys_common::interrupts::IsrDepth;
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+pub type Register = bool;
+
+/// The timer that generates tones, like on the Arduino Uno.
+pub const TONE_TIMER: Option<RduinoTimer> = Some(RduinoTimer::Timer2);
+
+// Every thread has its own board, but the statics of the crate are shared between all of them. On
+// real hardware disabling interrupts keeps everything else away from those statics, here a thread
+// also holds this lock for as long as its interrupts are disabled.
+static GLOBAL_LOCK: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static INTERRUPTS_ENABLED: Cell<bool> = Cell::new(true);
+    static ISR_DEPTH: IsrDepth = IsrDepth::new();
+}
+
+#[inline]
+pub unsafe fn disable_interrupts() -> Register {
+    let prev_enabled = INTERRUPTS_ENABLED.with(|enabled| enabled.replace(false));
+    if prev_enabled {
+        while GLOBAL_LOCK.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                         .is_err() {
+            thread::yield_now();
+        }
+    }
+    prev_enabled
+}
+
+#[inline]
+pub unsafe fn restore_interrupts(prev_enabled: Register) {
+    if prev_enabled {
+        INTERRUPTS_ENABLED.with(|enabled| enabled.set(true));
+        GLOBAL_LOCK.store(false, Ordering::Release);
+        board::run_pending_interrupts();
+    }
+}
+
+#[inline]
+pub fn interrupts_enabled() -> bool {
+    INTERRUPTS_ENABLED.with(|enabled| enabled.get())
+}
+
+#[inline]
+pub fn inside_isr() -> bool {
+    ISR_DEPTH.with(|depth| depth.get() > 0)
+}
+
+#[inline]
+#[doc(hidden)]
+pub fn __enter_isr() {
+    ISR_DEPTH.with(IsrDepth::enter);
+}
+
+#[inline]
+#[doc(hidden)]
+pub fn __exit_isr() {
+    ISR_DEPTH.with(IsrDepth::exit);
+}
@@ -0,0 +1,3 @@
+pub mod board;
+pub mod interrupts;
+pub mod statics;
@@ -0,0 +1,75 @@
+// Statics whose state belongs to the simulated board of the current thread.
+
+use sys::board;
+use sys_common::sync::CriticalSection;
+
+use std::any::Any;
+use std::boxed::Box;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// A static holding state of the board, such as which pins and ports are claimed.
+///
+/// Every simulated board starts out with a clone of the initial value. The values of statics
+/// created with `noinit` survive `board::restart`, like the `.noinit` section on real hardware.
+pub struct BoardLocal<T> {
+    init: T,
+    noinit: bool
+}
+
+// The initial value is only cloned while interrupts are disabled, which holds the global lock.
+unsafe impl<T: Send> Sync for BoardLocal<T> {}
+
+impl<T> BoardLocal<T> {
+    pub const fn new(value: T) -> BoardLocal<T> {
+        BoardLocal { init: value, noinit: false }
+    }
+
+    /// Like `new`, for a static whose value is kept on a software reset.
+    pub const fn noinit(value: T) -> BoardLocal<T> {
+        BoardLocal { init: value, noinit: true }
+    }
+}
+
+impl<T: Clone + 'static> BoardLocal<T> {
+    /// Run a closure with exclusive access to the value.
+    ///
+    /// The closure must not access the same static again.
+    pub fn with<R, F: FnOnce(&mut T) -> R>(&'static self, f: F) -> R {
+        // Interrupts stay disabled, like on the boards, so no routine can see the value while it is
+        // taken out of the board.
+        let _section = CriticalSection::enter();
+        let key = self as *const BoardLocal<T> as usize;
+        let value = board::with_board(|board| board.statics.remove(&key));
+        let mut value = match value {
+            Some((_, value)) => *value.downcast::<T>().unwrap(),
+            None => self.init.clone()
+        };
+        let result = f(&mut value);
+        let value = Box::new(value) as Box<Any>;
+        board::with_board(|board| board.statics.insert(key, (self.noinit, value)));
+        result
+    }
+}
+
+/// An index of a `Queue`.
+///
+/// Reading it orders the following accesses of the buffer after the read and writing it orders the
+/// preceding accesses before the write, so the halves see each other's elements. The halves may
+/// run on different threads, so the index is a real atomic.
+pub struct QueueIndex(AtomicU8);
+
+impl QueueIndex {
+    pub const fn new() -> QueueIndex {
+        QueueIndex(AtomicU8::new(0))
+    }
+
+    #[inline]
+    pub fn acquire(&self) -> u8 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn release(&self, index: u8) {
+        self.0.store(index, Ordering::Release);
+    }
+}
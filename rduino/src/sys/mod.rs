@@ -1,9 +1,16 @@
 pub use self::imp::*;
 
-#[cfg(target_arch = "avr")]
+#[cfg(not(feature = "host-sim"))]
+pub mod statics;
+
+#[cfg(all(target_arch = "avr", not(feature = "host-sim")))]
 #[path = "avr/mod.rs"]
 mod imp;
 
-#[cfg(target_arch = "arm")]
+#[cfg(all(target_arch = "arm", not(feature = "host-sim")))]
 #[path = "arm/mod.rs"]
 mod imp;
+
+#[cfg(feature = "host-sim")]
+#[path = "host/mod.rs"]
+mod imp;
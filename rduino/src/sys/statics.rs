@@ -0,0 +1,63 @@
+// Statics whose state belongs to the board the program runs on.
+
+use sync::Mutex;
+
+use core::cell::UnsafeCell;
+use core::{intrinsics, ptr};
+
+/// A static holding state of the board, such as which pins and ports are claimed.
+///
+/// The value is only accessed with interrupts disabled.
+pub struct BoardLocal<T> {
+    data: Mutex<T>
+}
+
+impl<T> BoardLocal<T> {
+    pub const fn new(value: T) -> BoardLocal<T> {
+        BoardLocal { data: Mutex::new(value) }
+    }
+
+    /// Like `new`, for a static that is placed in a section that is kept on a reset.
+    pub const fn noinit(value: T) -> BoardLocal<T> {
+        BoardLocal::new(value)
+    }
+
+    /// Run a closure with exclusive access to the value.
+    ///
+    /// The closure must not access the same static again.
+    #[inline]
+    pub fn with<R, F: FnOnce(&mut T) -> R>(&'static self, f: F) -> R {
+        f(&mut self.data.lock())
+    }
+}
+
+/// An index of a `Queue`.
+///
+/// Reading it orders the following accesses of the buffer after the read and writing it orders the
+/// preceding accesses before the write, so the halves see each other's elements. The halves can
+/// only interrupt each other on a single core, so volatile single byte accesses and compiler fences
+/// are enough.
+pub struct QueueIndex(UnsafeCell<u8>);
+
+impl QueueIndex {
+    pub const fn new() -> QueueIndex {
+        QueueIndex(UnsafeCell::new(0))
+    }
+
+    #[inline]
+    pub fn acquire(&self) -> u8 {
+        let index = unsafe { ptr::read_volatile(self.0.get()) };
+        unsafe {
+            intrinsics::atomic_singlethreadfence();
+        }
+        index
+    }
+
+    #[inline]
+    pub fn release(&self, index: u8) {
+        unsafe {
+            intrinsics::atomic_singlethreadfence();
+            ptr::write_volatile(self.0.get(), index);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Atomic access to `Copy` values of any type.
+//
+// Values with the size of an atomic integer that is natively supported by the target are accessed
+// with atomic instructions. All other values, and all values on targets without atomics such as
+// AVR, are accessed inside of a critical section.
+
+use sys_common::sync::CriticalSection;
+
+#[cfg(target_has_atomic = "8")]
+use core::sync::atomic::AtomicU8;
+#[cfg(target_has_atomic = "16")]
+use core::sync::atomic::AtomicU16;
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::AtomicU32;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+#[allow(unused_imports)]
+use core::sync::atomic::Ordering;
+
+use core::{mem, ptr};
+
+macro_rules! try_native {
+    ($ptr:ident, $atomic:ident, $int:ident, |$cell:ident| $body:expr) => {
+        if mem::size_of::<T>() == mem::size_of::<$int>()
+           && mem::align_of::<T>() >= mem::align_of::<$atomic>() {
+            let $cell = &*($ptr as *const $atomic);
+            return $body;
+        }
+    }
+}
+
+// Returns the result of `$body` from the enclosing function if the value can be accessed natively,
+// with `$cell` bound to the atomic integer overlaying the value.
+macro_rules! native {
+    ($ptr:ident, |$cell:ident| $body:expr) => {
+        #[cfg(target_has_atomic = "8")]
+        {
+            try_native!($ptr, AtomicU8, u8, |$cell| $body);
+        }
+        #[cfg(target_has_atomic = "16")]
+        {
+            try_native!($ptr, AtomicU16, u16, |$cell| $body);
+        }
+        #[cfg(target_has_atomic = "32")]
+        {
+            try_native!($ptr, AtomicU32, u32, |$cell| $body);
+        }
+        #[cfg(target_has_atomic = "64")]
+        {
+            try_native!($ptr, AtomicU64, u64, |$cell| $body);
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[inline]
+unsafe fn convert<T: Copy, U: Copy>(value: T) -> U {
+    mem::transmute_copy(&value)
+}
+
+#[inline]
+pub unsafe fn load<T: Copy>(ptr: *mut T) -> T {
+    native!(ptr, |cell| convert(cell.load(Ordering::SeqCst)));
+
+    let _section = CriticalSection::enter();
+    ptr::read_volatile(ptr)
+}
+
+#[inline]
+pub unsafe fn store<T: Copy>(ptr: *mut T, value: T) {
+    native!(ptr, |cell| cell.store(convert(value), Ordering::SeqCst));
+
+    let _section = CriticalSection::enter();
+    ptr::write_volatile(ptr, value);
+}
+
+#[inline]
+pub unsafe fn swap<T: Copy>(ptr: *mut T, value: T) -> T {
+    native!(ptr, |cell| convert(cell.swap(convert(value), Ordering::SeqCst)));
+
+    let _section = CriticalSection::enter();
+    let old = ptr::read_volatile(ptr);
+    ptr::write_volatile(ptr, value);
+    old
+}
+
+// Replaces the value with the result of `f` unless it returns `None`. Returns the old value.
+//
+// Natively, `f` may be called several times if the value is changed by an interrupt in between.
+pub unsafe fn update<T: Copy, F: FnMut(T) -> Option<T>>(ptr: *mut T, mut f: F) -> Result<T, T> {
+    native!(ptr, |cell| {
+        let mut bits = cell.load(Ordering::SeqCst);
+        loop {
+            let old: T = convert(bits);
+            match f(old) {
+                Some(new) => {
+                    match cell.compare_exchange_weak(bits, convert(new), Ordering::SeqCst,
+                                                     Ordering::SeqCst) {
+                        Ok(_) => break Ok(old),
+                        Err(actual) => bits = actual
+                    }
+                }
+                None => break Err(old)
+            }
+        }
+    });
+
+    let _section = CriticalSection::enter();
+    let old = ptr::read_volatile(ptr);
+    match f(old) {
+        Some(new) => {
+            ptr::write_volatile(ptr, new);
+            Ok(old)
+        }
+        None => Err(old)
+    }
+}
@@ -0,0 +1,68 @@
+// Values of any type stored inline, without allocating.
+//
+// The state of local interrupt service routines and the print targets are moved into static slots,
+// whose type can not depend on the type of the value. The value is stored as plain bytes together
+// with a function that drops it, and the owner of the slot keeps any other functions it needs.
+
+use core::{mem, ptr};
+
+/// The maximum size in bytes of an erased value.
+pub const MAX_SIZE: usize = 16;
+
+/// A value whose type has been erased.
+///
+/// Copying or dropping this structure does not copy or drop the value. The owner must make sure
+/// the value is dropped exactly once with `dispose`.
+#[derive(Clone, Copy)]
+pub struct Erased {
+    // Storage for the value, aligned for any type it may be.
+    value: [u64; MAX_SIZE / 8],
+    drop: unsafe fn(*mut u8)
+}
+
+/// An erased value that does not need to be dropped.
+pub const EMPTY: Erased = Erased {
+    value: [0; MAX_SIZE / 8],
+    drop: drop_nothing
+};
+
+impl Erased {
+    /// Erase the type of a value.
+    ///
+    /// Returns `None` and drops the value if it is larger than `MAX_SIZE` or needs a larger
+    /// alignment than `u64`.
+    pub fn new<T>(value: T) -> Option<Erased> {
+        if mem::size_of::<T>() > MAX_SIZE || mem::align_of::<T>() > mem::align_of::<u64>() {
+            return None;
+        }
+
+        let mut storage = [0; MAX_SIZE / 8];
+        unsafe {
+            ptr::write(storage.as_mut_ptr() as *mut T, value);
+        }
+        Some(Erased {
+            value: storage,
+            drop: drop_value::<T>
+        })
+    }
+
+    /// A pointer to the value, which can be cast back to a pointer to its original type.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.value.as_mut_ptr() as *mut u8
+    }
+
+    /// Drop the value.
+    #[inline]
+    pub fn dispose(mut self) {
+        unsafe {
+            (self.drop)(self.as_mut_ptr());
+        }
+    }
+}
+
+unsafe fn drop_value<T>(value: *mut u8) {
+    ptr::drop_in_place(value as *mut T)
+}
+
+unsafe fn drop_nothing(_: *mut u8) {}
@@ -0,0 +1,41 @@
+use core::cell::UnsafeCell;
+use core::{intrinsics, ptr};
+
+/// The number of interrupt service routines that are currently running.
+///
+/// Interrupts are nested when an ISR enables interrupts again, so a single flag would be cleared by
+/// the inner routine while the outer one is still running. The counter is not updated atomically,
+/// which is fine: a routine that interrupts an update always restores the count before the
+/// interrupted code resumes.
+pub struct IsrDepth {
+    depth: UnsafeCell<u8>
+}
+
+unsafe impl Sync for IsrDepth {}
+
+impl IsrDepth {
+    pub const fn new() -> IsrDepth {
+        IsrDepth { depth: UnsafeCell::new(0) }
+    }
+
+    #[inline]
+    pub fn get(&self) -> u8 {
+        unsafe { ptr::read_volatile(self.depth.get()) }
+    }
+
+    #[inline]
+    pub fn enter(&self) {
+        unsafe {
+            ptr::write_volatile(self.depth.get(), self.get().saturating_add(1));
+            intrinsics::atomic_singlethreadfence();
+        }
+    }
+
+    #[inline]
+    pub fn exit(&self) {
+        unsafe {
+            intrinsics::atomic_singlethreadfence();
+            ptr::write_volatile(self.depth.get(), self.get().saturating_sub(1));
+        }
+    }
+}
@@ -1,13 +1,29 @@
+#[cfg(not(feature = "host-sim"))]
+use panic::{self, PanicInfo};
+#[cfg(not(feature = "host-sim"))]
+use sync::AtomicCell;
+
+#[cfg(not(feature = "host-sim"))]
 use core::intrinsics;
 
+pub mod atomic;
+pub mod erased;
+#[cfg(any(target_arch = "avr", feature = "host-sim"))]
+pub mod interrupts;
+pub mod pins;
+pub mod serial;
 pub mod sync;
+pub mod time;
+pub mod timers;
 
+#[cfg(not(feature = "host-sim"))]
 extern {
     // FIXME: Workaround for https://github.com/rust-lang/rust/issues/28728
     // The actual type is extern fn() -> !, but empty loops in user code get optimized away
     fn __rduino_main();
 }
 
+#[cfg(not(feature = "host-sim"))]
 #[export_name = "setup"]
 #[doc(hidden)]
 pub extern "C" fn __rduino_setup() {
@@ -22,6 +38,7 @@ pub extern "C" fn __rduino_setup() {
     }
 }
 
+#[cfg(not(feature = "host-sim"))]
 #[export_name = "loop"]
 #[doc(hidden)]
 pub extern "C" fn __rduino_loop() {
@@ -30,3 +47,45 @@ pub extern "C" fn __rduino_loop() {
         intrinsics::abort();
     }
 }
+
+#[cfg(not(feature = "host-sim"))]
+#[export_name = "yield"]
+#[doc(hidden)]
+pub extern "C" fn __rduino_yield() {
+    // Called by the Arduino core while it is waiting, e.g. inside of `delay()`, which keeps the
+    // extended clock up to date even if the program sleeps for a long time.
+    ::time::millisecs64();
+}
+
+// Set when a panic starts. Formatting the payload or running the hook may panic again, which then
+// aborts right away instead of recursing.
+#[cfg(not(feature = "host-sim"))]
+static PANICKING: AtomicCell<bool> = AtomicCell::new(false);
+
+// On the simulated board panics are handled by `std`.
+#[cfg(not(feature = "host-sim"))]
+#[lang = "panic_fmt"]
+extern fn rust_begin_panic(message: ::core::fmt::Arguments, file: &'static str, line: u32) -> ! {
+    unsafe {
+        ::sys::interrupts::disable_interrupts();
+    }
+    if PANICKING.swap(true) {
+        unsafe {
+            intrinsics::abort();
+        }
+    }
+
+    let info = PanicInfo::__new(message, file, line);
+    panic::__record_panic(&info);
+
+    if !::sys::interrupts::inside_isr() {
+        let handler = panic::take_hook();
+        if let Some(handler) = handler {
+            handler(&info);
+        }
+    }
+
+    unsafe {
+        intrinsics::abort();
+    }
+}
@@ -0,0 +1,18 @@
+//! Pin conversions for the platform-specific extensions.
+//!
+//! The conversions are implemented in `pins`, but they can not be named outside of the crate, so
+//! the extensions can create pins in other modes without allowing users to forge them, e.g. an
+//! output pin whose hardware is configured as an input.
+
+use pins::{DigitalPin, InterruptPin, PinMode};
+use platform::raw::c_int;
+
+pub trait IntoMode {
+    /// Configure the hardware of the pin and change its type accordingly.
+    fn into_mode<NEW>(self, mode: PinMode) -> DigitalPin<NEW>;
+}
+
+pub trait IntoInterruptPin<PULL>: Sized {
+    /// Use the pin as the given interrupt, or give it back if the number is negative.
+    fn into_interrupt_pin(self, interrupt: c_int) -> Result<InterruptPin<PULL>, Self>;
+}
@@ -0,0 +1,12 @@
+//! Access to the port behind a serial handle.
+//!
+//! The ports are static objects of the Arduino library, so the raw pointer stays valid after the
+//! handle is closed. The trait can not be named outside of the crate, so the pointer can not be
+//! used to get around the ownership of a port.
+
+use ffi::RduinoSerial;
+
+pub trait AsRawSerial {
+    /// The port owned by this handle.
+    fn as_raw(&self) -> *mut RduinoSerial;
+}
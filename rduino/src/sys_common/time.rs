@@ -0,0 +1,27 @@
+/// Extends a wrapping 32-bit counter to 64 bits.
+///
+/// The counter must be updated at least once per wraparound of the 32-bit value, otherwise whole
+/// periods are missed.
+#[derive(Debug, Clone)]
+pub struct ExtendedCounter {
+    last: u32,
+    high: u32
+}
+
+impl ExtendedCounter {
+    pub const fn new() -> ExtendedCounter {
+        ExtendedCounter {
+            last: 0,
+            high: 0
+        }
+    }
+
+    /// Update the counter with the current 32-bit value and return the extended value.
+    pub fn update(&mut self, value: u32) -> u64 {
+        if value < self.last {
+            self.high = self.high.wrapping_add(1);
+        }
+        self.last = value;
+        (self.high as u64) << 32 | value as u64
+    }
+}
@@ -0,0 +1,60 @@
+// Reservations of the hardware timers.
+//
+// A timer can only run one interrupt service routine, and on AVR `tone()` reprograms `Timer2`, so
+// both timer interrupts and tones claim the timer they use here before touching it.
+
+use ffi::RduinoTimer;
+use io;
+use sys::interrupts::TONE_TIMER;
+use sys::statics::BoardLocal;
+
+// The number of `RduinoTimer` variants.
+const NUM_TIMERS: usize = 4;
+
+#[derive(Clone, Copy)]
+struct Reservations {
+    timers: [bool; NUM_TIMERS],
+    tone: bool
+}
+
+static RESERVATIONS: BoardLocal<Reservations> = BoardLocal::new(Reservations {
+    timers: [false; NUM_TIMERS],
+    tone: false
+});
+
+/// Claim a timer for an interrupt service routine.
+///
+/// Returns `TimerBusy` if another routine is attached to the timer or if a tone is generated with
+/// it.
+pub fn claim_timer(timer: RduinoTimer) -> io::Result<()> {
+    RESERVATIONS.with(|reservations| {
+        if reservations.timers[timer as usize] || reservations.tone && TONE_TIMER == Some(timer) {
+            return Err(io::Error::TimerBusy);
+        }
+        reservations.timers[timer as usize] = true;
+        Ok(())
+    })
+}
+
+pub fn release_timer(timer: RduinoTimer) {
+    RESERVATIONS.with(|reservations| reservations.timers[timer as usize] = false);
+}
+
+/// Claim the tone generator.
+///
+/// Returns `ToneBusy` if a tone is already generated or if an interrupt service routine is attached
+/// to the timer that generates tones.
+pub fn claim_tone() -> io::Result<()> {
+    RESERVATIONS.with(|reservations| {
+        let timer_busy = TONE_TIMER.map_or(false, |timer| reservations.timers[timer as usize]);
+        if reservations.tone || timer_busy {
+            return Err(io::Error::ToneBusy);
+        }
+        reservations.tone = true;
+        Ok(())
+    })
+}
+
+pub fn release_tone() {
+    RESERVATIONS.with(|reservations| reservations.tone = false);
+}
@@ -1,15 +1,18 @@
 //! Timing related functions.
 //!
+//! The raw clocks returned by `millisecs` and `microsecs` wrap around after about 49 days and 71
+//! minutes respectively. For measuring time an `Instant` is preferred, which does not wrap around
+//! and takes care of the arithmetic.
+//!
 //! **Note**: Many of these functions do not work properly when interrupts are disabled, which is
 //! generally the case inside of interrupt service routines, when a mutex lock is held or inside
 //! of a panic hook.
 
 use ffi;
+use sys::statics::BoardLocal;
+use sys_common::time::ExtendedCounter;
 
-// pub enum SystemTime {
-//     Millisecs(u32),
-//     Microsecs(u32)
-// }
+use core::ops::{Add, AddAssign, Sub, SubAssign};
 
 /// The system time in milliseconds.
 ///
@@ -18,6 +21,17 @@ pub fn millisecs() -> u32 {
     unsafe { ffi::rduino_millis()}
 }
 
+/// The system time in milliseconds as a 64-bit value that does not wrap around.
+///
+/// The 32-bit millisecond clock is extended by the runtime every time it waits, e.g. in
+/// `sleep_millisecs`, and every time this function is called. As long as either happens at least
+/// once every 49 days, the result is correct.
+///
+/// **Note**: This function might not return a reliable result when interrupts are disabled.
+pub fn millisecs64() -> u64 {
+    update_extended_millis()
+}
+
 /// The system time in microseconds.
 ///
 /// **Note**: This function might not return a reliable result when interrupts are disabled.
@@ -40,3 +54,238 @@ pub fn sleep_microsecs(us: u32) {
         ffi::rduino_delay_microseconds(us);
     }
 }
+
+/// Wait until the given duration has passed.
+///
+/// **Warning**: This function might cause a deadlock when interrupts are disabled.
+pub fn sleep(duration: Duration) {
+    let mut millis = duration.as_millis();
+    while millis > u32::max_value() as u64 {
+        sleep_millisecs(u32::max_value());
+        millis -= u32::max_value() as u64;
+    }
+    sleep_millisecs(millis as u32);
+    sleep_microsecs((duration.as_micros() % 1000) as u32);
+}
+
+fn update_extended_millis() -> u64 {
+    static EXTENDED_MILLIS: BoardLocal<ExtendedCounter> = BoardLocal::new(ExtendedCounter::new());
+
+    // The clock has to be read while the lock is held, otherwise an interrupt could update the
+    // counter with a newer value in between, which would look like a wraparound.
+    EXTENDED_MILLIS.with(|counter| counter.update(millisecs()))
+}
+
+fn microsecs64() -> u64 {
+    let estimate = millisecs64() * 1000;
+    // Both clocks are driven by the same timer, so the microsecond clock is never more than a
+    // few milliseconds away from the estimate and the wrapped difference is exact.
+    let offset = microsecs().wrapping_sub(estimate as u32) as i32;
+    (estimate as i64 + offset as i64).max(0) as u64
+}
+
+
+
+/// A span of time with microsecond precision.
+#[derive(Debug, Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Duration {
+    micros: u64
+}
+
+impl Duration {
+    /// Create a duration from the given number of seconds.
+    ///
+    /// # Panics
+    /// Panics if the duration does not fit in microseconds.
+    #[inline]
+    pub fn from_secs(secs: u64) -> Duration {
+        let micros = secs.checked_mul(1_000_000).expect("overflow in Duration::from_secs");
+        Duration { micros: micros }
+    }
+
+    /// Create a duration from the given number of milliseconds.
+    ///
+    /// # Panics
+    /// Panics if the duration does not fit in microseconds.
+    #[inline]
+    pub fn from_millis(millis: u64) -> Duration {
+        let micros = millis.checked_mul(1000).expect("overflow in Duration::from_millis");
+        Duration { micros: micros }
+    }
+
+    /// Create a duration from the given number of microseconds.
+    #[inline]
+    pub fn from_micros(micros: u64) -> Duration {
+        Duration { micros: micros }
+    }
+
+    /// The number of whole seconds in this duration.
+    #[inline]
+    pub fn as_secs(&self) -> u64 {
+        self.micros / 1_000_000
+    }
+
+    /// The number of whole milliseconds in this duration.
+    #[inline]
+    pub fn as_millis(&self) -> u64 {
+        self.micros / 1000
+    }
+
+    /// The number of microseconds in this duration.
+    #[inline]
+    pub fn as_micros(&self) -> u64 {
+        self.micros
+    }
+
+    /// Add two durations.
+    ///
+    /// Returns `None` if the result overflows.
+    #[inline]
+    pub fn checked_add(self, other: Duration) -> Option<Duration> {
+        self.micros.checked_add(other.micros).map(Duration::from_micros)
+    }
+
+    /// Subtract a duration from this one.
+    ///
+    /// Returns `None` if the result would be negative.
+    #[inline]
+    pub fn checked_sub(self, other: Duration) -> Option<Duration> {
+        self.micros.checked_sub(other.micros).map(Duration::from_micros)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    #[inline]
+    fn add(self, other: Duration) -> Duration {
+        self.checked_add(other).expect("overflow when adding durations")
+    }
+}
+
+impl AddAssign for Duration {
+    #[inline]
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    #[inline]
+    fn sub(self, other: Duration) -> Duration {
+        self.checked_sub(other).expect("overflow when subtracting durations")
+    }
+}
+
+impl SubAssign for Duration {
+    #[inline]
+    fn sub_assign(&mut self, other: Duration) {
+        *self = *self - other;
+    }
+}
+
+
+
+/// A point in time measured by a monotonic clock.
+///
+/// Instants are measured in microseconds since the board was reset. Unlike the values returned by
+/// `microsecs`, they do not wrap around, so durations between instants are correct no matter how
+/// far apart they are.
+///
+/// **Note**: The clock is extended in the same way as `millisecs64`.
+#[derive(Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Instant {
+    micros: u64
+}
+
+impl Instant {
+    /// The current instant.
+    ///
+    /// **Note**: This function might not return a reliable result when interrupts are disabled.
+    #[inline]
+    pub fn now() -> Instant {
+        Instant { micros: microsecs64() }
+    }
+
+    /// The time passed since this instant.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// The time passed from an earlier instant to this one.
+    ///
+    /// # Panics
+    /// Panics if `earlier` is later than this instant.
+    #[inline]
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.checked_duration_since(earlier).expect("supplied instant is later than self")
+    }
+
+    /// The time passed from an earlier instant to this one.
+    ///
+    /// Returns `None` if `earlier` is later than this instant.
+    #[inline]
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.micros.checked_sub(earlier.micros).map(Duration::from_micros)
+    }
+
+    /// The instant the given duration after this one.
+    ///
+    /// Returns `None` if the result can not be represented.
+    #[inline]
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.micros.checked_add(duration.micros).map(|micros| Instant { micros: micros })
+    }
+
+    /// The instant the given duration before this one.
+    ///
+    /// Returns `None` if the result would be before the board was reset.
+    #[inline]
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.micros.checked_sub(duration.micros).map(|micros| Instant { micros: micros })
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    #[inline]
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration).expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    #[inline]
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    #[inline]
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration).expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    #[inline]
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    #[inline]
+    fn sub(self, other: Instant) -> Duration {
+        self.duration_since(other)
+    }
+}
@@ -0,0 +1,285 @@
+//! Non-blocking software timers and a cooperative task scheduler.
+//!
+//! A `SoftTimer` answers the question "has the time passed yet?" without blocking, which lets a
+//! program do several things at once instead of calling `time::sleep_millisecs`. For programs with
+//! multiple periodic activities a `Scheduler` keeps track of the timers and runs tasks when they
+//! are due.
+//!
+//! Tasks registered with a scheduler borrow closures, so no allocation is needed. A typical program
+//! creates its tasks in the `rduino_main!` function, registers them and ends with
+//! `Scheduler::run`.
+
+use time::{Duration, Instant};
+
+use core::fmt;
+
+/// The maximum number of tasks a `Scheduler` can hold.
+pub const MAX_TASKS: usize = 8;
+
+/// A software timer.
+///
+/// The timer is checked by calling `poll`, which returns `true` once the timer has expired. A
+/// one-shot timer expires once, a periodic timer expires at every multiple of its period after it
+/// was started.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct SoftTimer {
+    deadline: Instant,
+    interval: Duration,
+    periodic: bool,
+    running: bool,
+    missed: u32
+}
+
+impl SoftTimer {
+    /// Create and start a timer that expires once after the given delay.
+    #[inline]
+    pub fn one_shot(delay: Duration) -> SoftTimer {
+        SoftTimer::new(delay, false)
+    }
+
+    /// Create and start a timer that expires every time the given period has passed.
+    ///
+    /// # Panics
+    /// Panics if the period is zero.
+    #[inline]
+    pub fn periodic(period: Duration) -> SoftTimer {
+        assert!(period != Duration::default(), "period of a periodic timer is zero");
+        SoftTimer::new(period, true)
+    }
+
+    fn new(interval: Duration, periodic: bool) -> SoftTimer {
+        SoftTimer {
+            deadline: Instant::now() + interval,
+            interval: interval,
+            periodic: periodic,
+            running: true,
+            missed: 0
+        }
+    }
+
+    /// Check whether the timer has expired.
+    ///
+    /// Returns `true` at most once per expiry. A one-shot timer stops after it expired. A periodic
+    /// timer is rearmed for its next deadline. If the timer was polled too late and whole periods
+    /// have passed since its deadline, those periods are skipped and counted as missed.
+    pub fn poll(&mut self) -> bool {
+        if !self.running {
+            return false;
+        }
+
+        let now = Instant::now();
+        if now < self.deadline {
+            return false;
+        }
+
+        if self.periodic {
+            let period_us = self.interval.as_micros();
+            let missed = now.duration_since(self.deadline).as_micros() / period_us;
+            self.missed = self.missed.saturating_add(missed as u32);
+            self.deadline += Duration::from_micros(period_us * (missed + 1));
+        } else {
+            self.running = false;
+        }
+        true
+    }
+
+    /// Restart the timer, counting from now.
+    #[inline]
+    pub fn restart(&mut self) {
+        self.deadline = Instant::now() + self.interval;
+        self.running = true;
+    }
+
+    /// Stop the timer.
+    ///
+    /// A stopped timer never expires until it is restarted.
+    #[inline]
+    pub fn cancel(&mut self) {
+        self.running = false;
+    }
+
+    /// Whether the timer is running.
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// The time left until the timer expires.
+    ///
+    /// Returns `None` if the timer is stopped and a zero duration if it has expired, but was not
+    /// polled yet.
+    #[inline]
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.running {
+            Some(self.deadline.checked_duration_since(Instant::now()).unwrap_or_default())
+        } else {
+            None
+        }
+    }
+
+    /// The total number of periods that were skipped because the timer was polled too late.
+    #[inline]
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+}
+
+
+
+/// Identifies a task registered with a `Scheduler`.
+///
+/// Identifiers are not reused when a task is removed, so a stale identifier never refers to a
+/// task that was registered later in the same slot.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct TaskId {
+    index: usize,
+    generation: u32
+}
+
+/// A report of a periodic task that did not run at one or more of its deadlines.
+///
+/// This happens when the scheduler is not run often enough, typically because another task takes
+/// too long.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Overrun {
+    task: TaskId,
+    missed: u32
+}
+
+impl Overrun {
+    /// The task that overran.
+    #[inline]
+    pub fn task(&self) -> TaskId {
+        self.task
+    }
+
+    /// The number of deadlines that were skipped.
+    #[inline]
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+}
+
+struct Task<'a> {
+    id: TaskId,
+    timer: SoftTimer,
+    run: &'a mut FnMut()
+}
+
+/// A cooperative scheduler running tasks at their deadlines.
+///
+/// Tasks are closures that are borrowed by the scheduler, so no allocation is needed. Up to
+/// `MAX_TASKS` tasks can be registered at the same time. Tasks are run one after another from
+/// `run` or `run_pending` and should return quickly, since a slow task delays all others.
+pub struct Scheduler<'a> {
+    tasks: [Option<Task<'a>>; MAX_TASKS],
+    generation: u32,
+    on_overrun: Option<&'a mut FnMut(Overrun)>
+}
+
+impl<'a> Scheduler<'a> {
+    /// Create a scheduler without any tasks.
+    pub fn new() -> Scheduler<'a> {
+        Scheduler {
+            tasks: [None, None, None, None, None, None, None, None],
+            generation: 0,
+            on_overrun: None
+        }
+    }
+
+    /// Run a task every time the given period has passed.
+    ///
+    /// Returns `None` if the scheduler is full.
+    ///
+    /// # Panics
+    /// Panics if the period is zero.
+    #[inline]
+    pub fn every(&mut self, period: Duration, task: &'a mut FnMut()) -> Option<TaskId> {
+        self.add(SoftTimer::periodic(period), task)
+    }
+
+    /// Run a task once after the given delay.
+    ///
+    /// The task is removed from the scheduler after it has run. Returns `None` if the scheduler is
+    /// full.
+    #[inline]
+    pub fn after(&mut self, delay: Duration, task: &'a mut FnMut()) -> Option<TaskId> {
+        self.add(SoftTimer::one_shot(delay), task)
+    }
+
+    fn add(&mut self, timer: SoftTimer, task: &'a mut FnMut()) -> Option<TaskId> {
+        self.tasks.iter().position(Option::is_none).map(|index| {
+            let id = TaskId { index: index, generation: self.generation };
+            self.generation = self.generation.wrapping_add(1);
+            self.tasks[index] = Some(Task { id: id, timer: timer, run: task });
+            id
+        })
+    }
+
+    /// Remove a task from the scheduler.
+    ///
+    /// Returns `false` if the task was not registered, e.g. because it was a one-shot task that
+    /// has already run.
+    pub fn cancel(&mut self, task: TaskId) -> bool {
+        let slot = &mut self.tasks[task.index];
+        if slot.as_ref().map_or(false, |registered| registered.id == task) {
+            *slot = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set a handler that is called every time a periodic task overran.
+    #[inline]
+    pub fn on_overrun(&mut self, handler: &'a mut FnMut(Overrun)) {
+        self.on_overrun = Some(handler);
+    }
+
+    /// Run all tasks that are due.
+    ///
+    /// Returns the number of tasks that were run.
+    pub fn run_pending(&mut self) -> usize {
+        let mut count = 0;
+        for index in 0..MAX_TASKS {
+            let (id, missed, done) = match self.tasks[index] {
+                Some(ref mut task) => {
+                    let missed_before = task.timer.missed();
+                    if !task.timer.poll() {
+                        continue;
+                    }
+                    (task.run)();
+                    count += 1;
+                    (task.id, task.timer.missed() - missed_before, !task.timer.is_running())
+                }
+                None => continue
+            };
+
+            if done {
+                self.tasks[index] = None;
+            }
+            if missed > 0 {
+                if let Some(ref mut handler) = self.on_overrun {
+                    handler(Overrun { task: id, missed: missed });
+                }
+            }
+        }
+        count
+    }
+
+    /// Run the tasks forever.
+    ///
+    /// This is typically the last call in the `rduino_main!` function.
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.run_pending();
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Scheduler<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let timers = self.tasks.iter().filter_map(|task| task.as_ref().map(|task| task.timer));
+        fmt.debug_list().entries(timers).finish()
+    }
+}
@@ -0,0 +1,768 @@
+#![cfg(feature = "host-sim")]
+#![cfg_attr(feature = "embedded-hal", feature(never_type))]
+
+#[cfg(feature = "embedded-hal")]
+extern crate embedded_hal;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "embedded-hal")]
+extern crate nb;
+#[macro_use]
+extern crate rduino;
+
+#[cfg(feature = "embedded-hal")]
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+#[cfg(feature = "embedded-hal")]
+use embedded_hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin};
+#[cfg(feature = "embedded-hal")]
+use embedded_hal::{serial, PwmPin};
+
+use rduino::executor::{self, Executor, Future, Poll};
+#[cfg(feature = "embedded-hal")]
+use rduino::hal::Delay;
+use rduino::interrupts::{self, InterruptMode};
+use rduino::io;
+use rduino::io::prelude::*;
+use rduino::panic::{DefaultHook, PanicInfo};
+use rduino::pins::{AnalogOutputPin, BitOrder, PinLevel, PinMode, Pins, ShiftRegister};
+use rduino::platform::host::interrupts::TimerExt;
+use rduino::platform::host::pins::DigitalPinExt;
+use rduino::prelude::*;
+use rduino::pulse::{Ultrasonic, measure_pwm};
+use rduino::random::{ArduinoRng, Rng, SeedableRng, XorShiftRng};
+use rduino::serial::{Serial, SerialMode, SerialPort};
+use rduino::sim;
+use rduino::sync::{AtomicCell, Mutex, Queue};
+use rduino::time::{self, Duration, Instant};
+use rduino::timer::{Overrun, Scheduler};
+
+use std::cell::Cell;
+use std::panic;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+#[test]
+fn digital_pins() {
+    let mut pins = Pins::take().unwrap();
+    assert!(Pins::take().is_none());
+
+    let led = pins.digital(13).unwrap().into_output();
+    assert!(pins.digital(13).is_none());
+    led.write(PinLevel::High);
+    assert_eq!(sim::mode(13), Some(PinMode::Output));
+    assert_eq!(sim::level(13), PinLevel::High);
+
+    let button = pins.digital(7).unwrap().into_pull_up_input();
+    assert_eq!(sim::mode(7), Some(PinMode::InputPullup));
+    assert_eq!(button.read(), PinLevel::High);
+    sim::set_input(7, PinLevel::Low);
+    assert_eq!(button.read(), PinLevel::Low);
+    sim::release_input(7);
+    assert_eq!(button.read(), PinLevel::High);
+
+    // Analog input `A0` is digital pin 14.
+    let a0 = pins.analog_input(0).unwrap();
+    assert!(pins.digital(14).is_none());
+    pins.release(a0);
+    let pwm = pins.digital(14).unwrap().into_pwm().unwrap_err();
+    assert_eq!(pwm.number(), 14);
+}
+
+#[test]
+fn tone() {
+    let mut pins = Pins::take().unwrap();
+    let buzzer = pins.digital(8).unwrap().into_output();
+    let other = pins.digital(9).unwrap().into_output();
+
+    let guard = buzzer.tone(440, Some(100)).unwrap();
+    let tone = sim::tone().unwrap();
+    assert_eq!((tone.pin(), tone.frequency()), (8, 440));
+    assert_eq!(other.tone(880, None).unwrap_err(), io::Error::ToneBusy);
+
+    // The tone stops after its duration, but the generator stays reserved until the guard is
+    // dropped.
+    sim::advance_millis(99);
+    assert!(sim::tone().is_some());
+    sim::advance_millis(1);
+    assert!(sim::tone().is_none());
+    assert_eq!(other.tone(880, None).unwrap_err(), io::Error::ToneBusy);
+
+    guard.stop();
+    let guard = other.tone(880, None).unwrap();
+    assert_eq!(sim::tone().map(|tone| tone.pin()), Some(9));
+    guard.stop();
+    assert!(sim::tone().is_none());
+}
+
+static LATCH_EDGES: AtomicCell<u32> = AtomicCell::new(0);
+
+#[test]
+fn shift_register() {
+    let mut pins = Pins::take().unwrap();
+    let data = pins.digital(4).unwrap();
+    let clock = pins.digital(5).unwrap().into_output();
+    let latch = pins.digital(6).unwrap().into_output();
+    let mut register = ShiftRegister::new(data, clock, Some(latch)).bit_order(BitOrder::LsbFirst);
+
+    // Count the edges on the latch pin with its pin change interrupt.
+    let latch_interrupt = rduino::ffi::RDUINO_PIN_CHANGE_INTERRUPT_BASE as u8 + 6;
+    let guard = rduino_isr!(|| {
+        LATCH_EDGES.fetch_add(1);
+    }).attach(latch_interrupt, InterruptMode::Change).unwrap();
+
+    register.write_bytes(&[0x01, 0x80]);
+    assert_eq!(sim::take_shifted_out(4), vec![0x01, 0x80]);
+    assert_eq!(LATCH_EDGES.swap(0), 2);
+
+    // Formatted output is only latched once, when it is flushed.
+    write!(register, "{}{}", 1, 2).unwrap();
+    assert_eq!(sim::take_shifted_out(4), b"12");
+    assert_eq!(LATCH_EDGES.load(), 0);
+    register.flush().unwrap();
+    assert_eq!(LATCH_EDGES.load(), 2);
+    guard.detach();
+
+    // The simulated registers shift on the rising edge of the clock, so a bit lost before the
+    // first read would shift every byte.
+    sim::push_shift_in(4, &[0xa5, 0x3c]);
+    let mut buf = [0; 2];
+    register.read_bytes(&mut buf);
+    assert_eq!(buf, [0xa5, 0x3c]);
+    assert_eq!(sim::level(5), PinLevel::Low);
+    sim::push_shift_in(4, &[0x81]);
+    register.read_bytes(&mut buf);
+    assert_eq!(buf, [0x81, 0]);
+}
+
+#[test]
+fn pulses() {
+    let mut pins = Pins::take().unwrap();
+    let trigger = pins.digital(2).unwrap().into_output();
+    let echo = pins.digital(3).unwrap().into_floating_input();
+    let sensor = Ultrasonic::new(trigger, echo).timeout(Duration::from_secs(10));
+
+    // 5831 µs at 343 m/s is one meter there and back.
+    sim::push_pulse(3, PinLevel::High, 5831);
+    assert_eq!(sensor.distance_mm(), Some(1000));
+
+    // A distant echo no longer overflows the calculation.
+    sim::push_pulse(3, PinLevel::High, 9_000_000);
+    assert_eq!(sensor.distance_mm(), Some(1_543_500));
+
+    // Missing pulses time out and the clock advances while waiting.
+    let start = sim::micros();
+    assert_eq!(sensor.ping(), None);
+    assert!(sim::micros() - start >= 10_000_000);
+
+    let input = pins.digital(4).unwrap().into_floating_input();
+    sim::push_pulse(4, PinLevel::High, 1500);
+    sim::push_pulse(4, PinLevel::Low, 500);
+    let pwm = measure_pwm(&input, Duration::from_millis(10)).unwrap();
+    assert_eq!(pwm.high(), Duration::from_micros(1500));
+    assert_eq!(pwm.period(), Duration::from_millis(2));
+    assert_eq!(pwm.frequency(), 500);
+    assert_eq!(pwm.duty_cycle(), 0.75);
+
+    sim::push_pulse(4, PinLevel::High, 20);
+    assert_eq!(input.measure_pulse(PinLevel::High, Duration::from_micros(10)), None);
+}
+
+#[test]
+fn random() {
+    // The first output of xorshift32 for a seed of one.
+    let mut xorshift = XorShiftRng::from_seed(1);
+    assert_eq!(xorshift.next_u32(), 270369);
+    assert_ne!(XorShiftRng::from_seed(0).next_u32(), 0);
+
+    // Reseeding restarts the sequence, for the Arduino generator as well.
+    let mut arduino = ArduinoRng::from_seed(42);
+    let first: Vec<u32> = (0..8).map(|_| arduino.next_u32()).collect();
+    arduino.reseed(42);
+    let second: Vec<u32> = (0..8).map(|_| arduino.next_u32()).collect();
+    assert_eq!(first, second);
+
+    let mut a = XorShiftRng::from_seed(1234);
+    let mut b = a.clone();
+    let mut buf_a = [0; 7];
+    let mut buf_b = [0; 7];
+    a.fill_bytes(&mut buf_a);
+    b.fill_bytes(&mut buf_b);
+    assert_eq!(buf_a, buf_b);
+
+    let mut seen = [false; 7];
+    for _ in 0..1000 {
+        let value = a.gen_range(-3, 4);
+        assert!(-3 <= value && value < 4);
+        seen[(value + 3) as usize] = true;
+    }
+    assert!(seen.iter().all(|&seen| seen));
+    for _ in 0..1000 {
+        assert_eq!(a.gen_range(5, 6), 5);
+        assert!(arduino.gen_range(i32::min_value(), i32::max_value()) < i32::max_value());
+    }
+}
+
+#[test]
+#[should_panic(expected = "empty range")]
+fn random_empty_range() {
+    XorShiftRng::from_seed(1).gen_range(3, 3);
+}
+
+#[test]
+fn clock() {
+    sim::advance_millis(1500);
+    assert_eq!(time::millisecs(), 1500);
+    time::sleep_microsecs(250);
+    assert_eq!(time::microsecs(), 1_500_250);
+    assert_eq!(sim::micros(), 1_500_250);
+}
+
+#[test]
+fn extended_clock() {
+    let start = Instant::now();
+    sim::advance_millis(u32::max_value() as u64);
+    assert_eq!(time::millisecs64(), u32::max_value() as u64);
+
+    // Both 32-bit clocks wrap around here, the extended clocks keep counting.
+    sim::advance_millis(2);
+    assert_eq!(time::millisecs(), 1);
+    assert_eq!(time::millisecs64(), u32::max_value() as u64 + 2);
+    assert_eq!(start.elapsed(), Duration::from_millis(u32::max_value() as u64 + 2));
+
+    let earlier = Instant::now();
+    time::sleep(Duration::from_micros(2500));
+    assert_eq!(earlier.elapsed().as_micros(), 2500);
+    assert!(earlier.checked_duration_since(Instant::now()).is_none());
+}
+
+#[test]
+#[should_panic(expected = "overflow in Duration::from_secs")]
+fn duration_overflow() {
+    Duration::from_secs(u64::max_value() / 1000);
+}
+
+#[test]
+fn scheduler() {
+    let blinks = Cell::new(0);
+    let overruns = Cell::new(0);
+    let mut blink = || blinks.set(blinks.get() + 1);
+    let mut report = |overrun: Overrun| overruns.set(overruns.get() + overrun.missed());
+
+    let mut scheduler = Scheduler::new();
+    scheduler.every(Duration::from_millis(250), &mut blink).unwrap();
+    scheduler.on_overrun(&mut report);
+
+    assert_eq!(scheduler.run_pending(), 0);
+    sim::advance_millis(250);
+    assert_eq!(scheduler.run_pending(), 1);
+    assert_eq!(scheduler.run_pending(), 0);
+
+    // Three deadlines pass without the scheduler running, two of them are skipped.
+    sim::advance_millis(750);
+    assert_eq!(scheduler.run_pending(), 1);
+    assert_eq!(blinks.get(), 2);
+    assert_eq!(overruns.get(), 2);
+}
+
+#[test]
+fn scheduler_cancel() {
+    let runs = Cell::new(0);
+    let mut once = || runs.set(runs.get() + 1);
+    let mut other = || runs.set(runs.get() + 10);
+
+    let mut scheduler = Scheduler::new();
+    let stale = scheduler.after(Duration::from_millis(10), &mut once).unwrap();
+    sim::advance_millis(10);
+    assert_eq!(scheduler.run_pending(), 1);
+
+    // The new task reuses the slot of the finished one, but the old identifier does not match it.
+    let task = scheduler.every(Duration::from_millis(10), &mut other).unwrap();
+    assert!(task != stale);
+    assert!(!scheduler.cancel(stale));
+    sim::advance_millis(10);
+    assert_eq!(scheduler.run_pending(), 1);
+    assert_eq!(runs.get(), 11);
+
+    assert!(scheduler.cancel(task));
+    assert!(!scheduler.cancel(task));
+}
+
+#[test]
+fn executor() {
+    let serial = Serial::open(SerialPort::HardwareOpen).unwrap();
+    let mut buf = [0; 4];
+    let reads = Cell::new(0);
+    let delays = Cell::new(0);
+    let start = sim::micros();
+    {
+        let mut read = executor::read_exact(&serial, &mut buf);
+        let mut reader = executor::poll_fn(|waker| {
+            reads.set(reads.get() + 1);
+            read.poll(waker)
+        });
+
+        // The data arrives once the delay has passed.
+        let mut delay = executor::delay(Duration::from_millis(20));
+        let mut sender = executor::poll_fn(|waker| {
+            delays.set(delays.get() + 1);
+            let poll = delay.poll(waker);
+            if poll == Poll::Ready(()) {
+                sim::push_serial_input(SerialPort::HardwareOpen, b"ping");
+            }
+            poll
+        });
+
+        let mut executor = Executor::take().unwrap();
+        assert!(executor.spawn(&mut reader));
+        assert!(executor.spawn(&mut sender));
+        executor.run();
+    }
+
+    // Each task is only polled when it is spawned and once its event has occurred.
+    assert_eq!(&buf, b"ping");
+    assert_eq!((reads.get(), delays.get()), (2, 2));
+    assert_eq!(sim::micros() - start, 20_000);
+}
+
+#[test]
+fn executor_without_deadline() {
+    let serial = Serial::open(SerialPort::HardwareOpen).unwrap();
+    let mut buf = [0; 4];
+    let start = sim::micros();
+    {
+        // Only a timer interrupt provides the data, so the executor has to let time pass.
+        let timer = rduino_isr!(|| sim::push_serial_input(SerialPort::HardwareOpen, b"ping"))
+            .attach_timer(TimerExt::Timer1, 100)
+            .unwrap();
+        let mut read = executor::read_exact(&serial, &mut buf);
+        let mut executor = Executor::take().unwrap();
+        assert!(executor.spawn(&mut read));
+        executor.run();
+        timer.detach();
+    }
+
+    assert_eq!(&buf, b"ping");
+    assert_eq!(sim::micros() - start, 10_000);
+}
+
+#[test]
+fn serial() {
+    let mut serial = Serial::open(SerialPort::HardwareOpen).unwrap();
+    serial.reset(SerialMode::new().speed(115200)).unwrap();
+    assert_eq!(sim::serial_speed(SerialPort::HardwareOpen), Some(115200));
+
+    sim::push_serial_input(SerialPort::HardwareOpen, b"ping");
+    let mut buf = [0; 4];
+    serial.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+
+    write!(serial, "pong {}", 42).unwrap();
+    assert_eq!(sim::take_serial_output(SerialPort::HardwareOpen), b"pong 42");
+
+    println!("hello");
+    assert_eq!(sim::take_serial_output(SerialPort::Monitor), b"hello\n");
+
+    // Each port can only be open once, the monitor and hardware port share the same one.
+    assert_eq!(Serial::open(SerialPort::HardwareOpen).unwrap_err(), io::Error::PortBusy);
+    let monitor = Serial::open(SerialPort::Monitor).unwrap();
+    assert_eq!(Serial::open(SerialPort::Hardware).unwrap_err(), io::Error::PortBusy);
+    assert_eq!(Serial::open(SerialPort::UsbVirtual).unwrap_err(), io::Error::PortUnavailable);
+
+    // Closing a port ends it and lets it be opened again.
+    serial.close();
+    assert_eq!(sim::serial_speed(SerialPort::HardwareOpen), None);
+    assert!(Serial::open(SerialPort::HardwareOpen).is_ok());
+    drop(monitor);
+}
+
+#[test]
+fn serial_dropped_in_isr() {
+    let serial = Serial::open(SerialPort::HardwareOpen).unwrap();
+    serial.reset(SerialMode::new()).unwrap();
+    let mut serial = Some(serial);
+    let guard = rduino_isr_local!(move || {
+        serial.take();
+    }).attach(0u8, InterruptMode::Rising).unwrap();
+
+    // The port is given back without ending it.
+    assert!(sim::trigger_interrupt(0));
+    assert_eq!(sim::serial_speed(SerialPort::HardwareOpen), Some(9600));
+    Serial::open(SerialPort::HardwareOpen).unwrap().close();
+    assert_eq!(sim::serial_speed(SerialPort::HardwareOpen), None);
+    guard.detach();
+}
+
+#[cfg(feature = "embedded-hal")]
+#[test]
+fn hal() {
+    let mut pins = Pins::take().unwrap();
+    let input = pins.digital(7).unwrap().into_pull_up_input();
+    assert_eq!(input.is_high(), Ok(true));
+    sim::set_input(7, PinLevel::Low);
+    assert_eq!(input.is_low(), Ok(true));
+
+    let mut output = pins.digital(13).unwrap().into_output();
+    OutputPin::set_high(&mut output).unwrap();
+    assert_eq!(sim::level(13), PinLevel::High);
+    assert_eq!(output.is_set_high(), Ok(true));
+
+    // A load that pulls the output down does not change the level it is set to.
+    sim::set_input(13, PinLevel::Low);
+    assert_eq!(output.read(), PinLevel::Low);
+    assert_eq!(output.is_set_high(), Ok(true));
+    sim::release_input(13);
+
+    // Resolutions that do not fit in a `u16` are clamped.
+    let mut pwm = pins.digital(9).unwrap().into_pwm().unwrap();
+    AnalogOutputPin::set_resolution(10);
+    assert_eq!(pwm.get_max_duty(), 1023);
+    pwm.set_duty(512);
+    assert_eq!(sim::analog_output(9), Some(512));
+    AnalogOutputPin::set_resolution(40);
+    assert_eq!(pwm.get_max_duty(), u16::max_value());
+    AnalogOutputPin::set_resolution(8);
+
+    let mut serial = Serial::open(SerialPort::HardwareOpen).unwrap();
+    assert_eq!(serial::Read::read(&mut serial), Err(nb::Error::WouldBlock));
+    sim::push_serial_input(SerialPort::HardwareOpen, b"a");
+    assert_eq!(serial::Read::read(&mut serial), Ok(b'a'));
+    serial::Write::write(&mut serial, b'b').unwrap();
+    serial::Write::flush(&mut serial).unwrap();
+    assert_eq!(sim::take_serial_output(SerialPort::HardwareOpen), b"b");
+
+    let start = sim::micros();
+    let mut delay = Delay::new();
+    delay.delay_ms(5u8);
+    delay.delay_us(250u32);
+    assert_eq!(sim::micros() - start, 5250);
+}
+
+#[test]
+fn print_target() {
+    let serial = Serial::open(SerialPort::HardwareOpen).unwrap();
+    serial.reset(SerialMode::new()).unwrap();
+    let guard = io::set_print(serial).unwrap();
+    println!("redirected");
+    assert_eq!(sim::take_serial_output(SerialPort::HardwareOpen), b"redirected\n");
+    assert!(sim::take_serial_output(SerialPort::Monitor).is_empty());
+
+    let other = Serial::open(SerialPort::Monitor).unwrap();
+    assert_eq!(io::set_print(other).unwrap_err(), io::Error::NoFreeSlot);
+
+    // Restoring drops the target, which closes its port.
+    guard.restore();
+    assert_eq!(sim::serial_speed(SerialPort::HardwareOpen), None);
+    println!("restored");
+    assert_eq!(sim::take_serial_output(SerialPort::Monitor), b"restored\n");
+}
+
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::Other)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn error_output() {
+    eprintln!("error {}", 1);
+    assert_eq!(sim::take_serial_output(SerialPort::Monitor), b"error 1\n");
+
+    let serial = Serial::open(SerialPort::HardwareOpen).unwrap();
+    let eprint = io::set_eprint(serial).unwrap();
+    eprintln!("error {}", 2);
+    println!("output");
+    assert_eq!(sim::take_serial_output(SerialPort::HardwareOpen), b"error 2\n");
+    assert_eq!(sim::take_serial_output(SerialPort::Monitor), b"output\n");
+
+    // Failures are returned by the `try_` variants and ignored by the error output.
+    let _print = io::set_print(Broken).unwrap();
+    assert_eq!(try_println!("output"), Err(io::Error::Other));
+    eprint.restore();
+    let _eprint = io::set_eprint(Broken).unwrap();
+    eprintln!("error {}", 3);
+}
+
+#[test]
+fn dbg() {
+    let value = dbg!(1 + 2) * 2;
+    assert_eq!(value, 6);
+    let output = String::from_utf8(sim::take_serial_output(SerialPort::Monitor)).unwrap();
+    assert!(output.starts_with("[tests/host_sim.rs:"), "{}", output);
+    assert!(output.ends_with("] 1 + 2 = 3\n"), "{}", output);
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn logger() {
+    rduino::logger::init(log::LevelFilter::Info).unwrap();
+    sim::advance_millis(1500);
+    info!("sensor ready");
+    debug!("too verbose");
+    assert_eq!(sim::take_serial_output(SerialPort::Monitor),
+               &b"[1500 INFO  host_sim] sensor ready\n"[..]);
+}
+
+#[test]
+fn panic_hook() {
+    DefaultHook::new().install().unwrap();
+    let hook = rduino::panic::take_hook().unwrap();
+    hook(&PanicInfo::__new(format_args!("bad {}", 1), "src/main.rs", 7));
+    assert_eq!(sim::take_serial_output(SerialPort::Monitor),
+               &b"panicked at 'bad 1', src/main.rs:7\n"[..]);
+
+    // The LED blinks until the board is reset, which also resets the clock.
+    DefaultHook::new().report(false).led(13).reset_after(500).install().unwrap();
+    let hook = rduino::panic::take_hook().unwrap();
+    sim::advance_millis(100);
+    hook(&PanicInfo::__new(format_args!("bad {}", 2), "src/main.rs", 8));
+    assert_eq!(sim::micros(), 0);
+    assert_eq!(sim::mode(13), None);
+}
+
+#[test]
+fn last_panic() {
+    assert!(rduino::panic::last_panic().is_none());
+    rduino::panic::__record_panic(&PanicInfo::__new(format_args!("{:>70}", "timed out"),
+                                                    "src/sensor.rs", 42));
+
+    // The message is truncated, and the panic survives a software reset but not a power cycle.
+    sim::restart();
+    let last = rduino::panic::last_panic().unwrap();
+    assert_eq!(last.message().len(), rduino::panic::MAX_PANIC_MESSAGE_LEN);
+    assert_eq!((last.file(), last.line()), ("src/sensor.rs", 42));
+    sim::reset();
+    assert!(rduino::panic::last_panic().is_none());
+
+    // The EEPROM backup does survive a power cycle, once it is set up again.
+    assert_eq!(rduino::panic::set_eeprom_backup(1000), Err(io::Error::InvalidAddress));
+    rduino::panic::set_eeprom_backup(16).unwrap();
+    rduino::panic::__record_panic(&PanicInfo::__new(format_args!("bad {}", 3), "src/main.rs", 9));
+    sim::reset();
+    assert!(rduino::panic::last_panic().is_none());
+    rduino::panic::set_eeprom_backup(16).unwrap();
+    let last = rduino::panic::last_panic().unwrap();
+    assert_eq!(last.to_string(), "panicked at 'bad 3', src/main.rs:9");
+    rduino::panic::clear_last_panic();
+    assert!(rduino::panic::last_panic().is_none());
+}
+
+#[test]
+fn eeprom_wraps() {
+    // Accesses past the end of the EEPROM wrap around to its start, like on the AVR.
+    let length = unsafe { rduino::ffi::rduino_eeprom_length() };
+    let address = (length - 2) as u16;
+    unsafe {
+        rduino::ffi::rduino_eeprom_write(address, [1, 2, 3, 4].as_ptr(), 4);
+    }
+    let mut buf = [0; 4];
+    unsafe {
+        rduino::ffi::rduino_eeprom_read(address, buf.as_mut_ptr(), 4);
+    }
+    assert_eq!(buf, [1, 2, 3, 4]);
+    unsafe {
+        rduino::ffi::rduino_eeprom_read(0, buf.as_mut_ptr(), 2);
+    }
+    assert_eq!(buf[..2], [3, 4]);
+}
+
+static EDGES: Mutex<u32> = Mutex::new(0);
+
+#[test]
+fn interrupts() {
+    let mut pins = Pins::take().unwrap();
+    let pin = pins.digital(2).unwrap().into_floating_input().into_interrupt().unwrap();
+    let guard = rduino_isr!(|| *EDGES.lock() += 1).attach(&pin, InterruptMode::Rising).unwrap();
+
+    sim::set_input(2, PinLevel::High);
+    sim::set_input(2, PinLevel::Low);
+    assert!(sim::trigger_interrupt(0));
+    assert_eq!(*EDGES.lock(), 2);
+
+    // Interrupts triggered inside of a critical section are deferred until it ends.
+    {
+        let mut edges = EDGES.lock();
+        sim::set_input(2, PinLevel::High);
+        *edges += 10;
+    }
+    assert_eq!(*EDGES.lock(), 13);
+
+    // The guard borrows the pin, so it can only be converted back once the routine is detached.
+    guard.detach();
+    assert!(!sim::trigger_interrupt(0));
+    pins.release(pin.into_digital());
+}
+
+#[test]
+fn nested_interrupts() {
+    assert!(!interrupts::inside_isr());
+    interrupts::__enter_isr();
+    interrupts::__enter_isr();
+    interrupts::__exit_isr();
+
+    // The outer routine is still running, so the checks must still be active.
+    assert!(interrupts::inside_isr());
+    assert!(panic::catch_unwind(|| Pins::take()).is_err());
+
+    interrupts::__exit_isr();
+    assert!(!interrupts::inside_isr());
+}
+
+#[test]
+fn local_interrupts() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let isr_count = count.clone();
+    let guard = rduino_isr_local!(move || {
+        isr_count.fetch_add(1, Ordering::SeqCst);
+    }).attach(0u8, InterruptMode::Rising).unwrap();
+
+    assert!(sim::trigger_interrupt(0));
+    assert!(sim::trigger_interrupt(0));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    // Detaching drops the state of the routine.
+    guard.detach();
+    assert!(!sim::trigger_interrupt(0));
+    assert_eq!(Arc::strong_count(&count), 1);
+}
+
+static EVENTS: Queue<[u8; 4]> = Queue::new([0; 4]);
+
+#[test]
+fn queue() {
+    let (mut producer, mut consumer) = EVENTS.split().unwrap();
+    assert!(EVENTS.split().is_none());
+    assert_eq!(EVENTS.capacity(), 3);
+
+    let guard = rduino_isr_local!(move || {
+        let _ = producer.enqueue(sim::micros() as u8);
+    }).attach(1u8, InterruptMode::Falling).unwrap();
+
+    for time in 1..6 {
+        sim::advance_micros(1);
+        sim::trigger_interrupt(1);
+        assert_eq!(consumer.len(), time.min(3));
+    }
+    assert_eq!(consumer.dequeue(), Some(1));
+    assert_eq!(consumer.dequeue(), Some(2));
+
+    // The ring buffer wraps around.
+    sim::advance_micros(1);
+    sim::trigger_interrupt(1);
+    assert_eq!(consumer.dequeue(), Some(3));
+    assert_eq!(consumer.dequeue(), Some(6));
+    assert_eq!(consumer.dequeue(), None);
+    guard.detach();
+}
+
+static NUMBERS: Queue<[u32; 16]> = Queue::new([0; 16]);
+
+#[test]
+fn queue_threads() {
+    let (mut producer, mut consumer) = NUMBERS.split().unwrap();
+    let thread = thread::spawn(move || {
+        for number in 0..10_000 {
+            while producer.enqueue(number).is_err() {
+                thread::yield_now();
+            }
+        }
+    });
+
+    let mut expected = 0;
+    while expected < 10_000 {
+        match consumer.dequeue() {
+            Some(number) => {
+                assert_eq!(number, expected);
+                expected += 1;
+            }
+            None => thread::yield_now()
+        }
+    }
+    thread.join().unwrap();
+    assert!(consumer.is_empty());
+}
+
+static PULSES: AtomicCell<u32> = AtomicCell::new(0);
+
+#[test]
+fn atomic_cell() {
+    let guard = rduino_isr!(|| {
+        PULSES.fetch_add(1);
+    }).attach(0u8, InterruptMode::Falling).unwrap();
+    sim::trigger_interrupt(0);
+    sim::trigger_interrupt(0);
+    assert_eq!(PULSES.swap(0), 2);
+    assert_eq!(PULSES.fetch_sub(1), 0);
+    assert_eq!(PULSES.load(), u32::max_value());
+    guard.detach();
+
+    // Three bytes have no native atomic, so a critical section is used instead.
+    let color = AtomicCell::new((0u8, 0u8, 0u8));
+    assert_eq!(color.compare_exchange((0, 0, 0), (255, 0, 0)), Ok((0, 0, 0)));
+    assert_eq!(color.compare_exchange((0, 0, 0), (0, 255, 0)), Err((255, 0, 0)));
+    color.store((0, 0, 255));
+    assert_eq!(color.into_inner(), (0, 0, 255));
+}
+
+static CHANGES: Mutex<u32> = Mutex::new(0);
+
+#[test]
+fn pin_change_interrupts() {
+    let mut pins = Pins::take().unwrap();
+    // Pin 7 has no external interrupt, but it does have a pin change interrupt.
+    let pin = pins.digital(7).unwrap().into_pull_up_input().into_interrupt().unwrap_err();
+    let pin = pin.into_pin_change_interrupt().unwrap();
+
+    let result = rduino_isr!(|| ()).attach(&pin, InterruptMode::Rising);
+    assert_eq!(result.unwrap_err(), io::Error::UnsupportedInterruptMode);
+
+    let guard = rduino_isr!(|| *CHANGES.lock() += 1).attach(&pin, InterruptMode::Change).unwrap();
+    sim::set_input(7, PinLevel::Low);
+    sim::set_input(7, PinLevel::High);
+    assert_eq!(*CHANGES.lock(), 2);
+
+    guard.detach();
+    sim::set_input(7, PinLevel::Low);
+    assert_eq!(*CHANGES.lock(), 2);
+}
+
+static TICKS: Mutex<u32> = Mutex::new(0);
+
+#[test]
+fn timer_interrupts() {
+    let guard = rduino_isr!(|| *TICKS.lock() += 1).attach_timer(TimerExt::Timer1, 1000).unwrap();
+    sim::advance_millis(10);
+    assert_eq!(*TICKS.lock(), 10);
+    time::sleep_millisecs(5);
+    assert_eq!(*TICKS.lock(), 15);
+
+    // The 8-bit timer can not count long enough for 10 Hz.
+    let result = rduino_isr!(|| ()).attach_timer(TimerExt::Timer2, 10);
+    assert_eq!(result.unwrap_err(), io::Error::UnsupportedFrequency);
+
+    // A timer runs one routine at a time, and `Timer2` also generates tones.
+    let result = rduino_isr!(|| ()).attach_timer(TimerExt::Timer1, 500);
+    assert_eq!(result.unwrap_err(), io::Error::TimerBusy);
+    let mut pins = Pins::take().unwrap();
+    let buzzer = pins.digital(8).unwrap().into_output();
+    let tone = buzzer.tone(440, None).unwrap();
+    let result = rduino_isr!(|| ()).attach_timer(TimerExt::Timer2, 1000);
+    assert_eq!(result.unwrap_err(), io::Error::TimerBusy);
+    tone.stop();
+    let timer2 = rduino_isr!(|| ()).attach_timer(TimerExt::Timer2, 1000).unwrap();
+    assert_eq!(buzzer.tone(440, None).unwrap_err(), io::Error::ToneBusy);
+    timer2.detach();
+
+    guard.detach();
+    sim::advance_millis(10);
+    assert_eq!(*TICKS.lock(), 15);
+    let guard = rduino_isr!(|| ()).attach_timer(TimerExt::Timer1, 500).unwrap();
+    guard.detach();
+}